@@ -0,0 +1,77 @@
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Error {
+    /// credentials were rejected or don't allow the request
+    Auth(String),
+    NotFound(String),
+    /// the metrics api isn't served or has no data for the object
+    MetricsUnavailable(String),
+    Parse(String),
+    /// any other api or connection error
+    Api(String),
+}
+
+impl Error {
+    /// maps an error from the metrics api, where a missing api or object means there is no usage data
+    pub fn from_metrics(e: kube::Error) -> Error {
+        match &e {
+            kube::Error::Api(r) if r.code == 404 || r.code == 503 => Error::MetricsUnavailable(e.to_string()),
+            _ => Error::from(e),
+        }
+    }
+
+    pub fn from_io(e: std::io::Error, message: String) -> Error {
+        let message = format!("{} {}", message, e);
+
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound(message),
+            std::io::ErrorKind::PermissionDenied => Error::Auth(message),
+            _ => Error::Parse(message),
+        }
+    }
+
+    pub fn context(self, context: &str) -> Error {
+        let wrap = |message: String| format!("{} {}", context, message);
+
+        match self {
+            Error::Auth(m) => Error::Auth(wrap(m)),
+            Error::NotFound(m) => Error::NotFound(wrap(m)),
+            Error::MetricsUnavailable(m) => Error::MetricsUnavailable(wrap(m)),
+            Error::Parse(m) => Error::Parse(wrap(m)),
+            Error::Api(m) => Error::Api(wrap(m)),
+        }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Api(_) => 1,
+            Error::Auth(_) => 2,
+            Error::NotFound(_) => 3,
+            Error::MetricsUnavailable(_) => 4,
+            Error::Parse(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Auth(m) | Error::NotFound(m) | Error::MetricsUnavailable(m) | Error::Parse(m) | Error::Api(m) => write!(f, "{}", m),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<kube::Error> for Error {
+    fn from(e: kube::Error) -> Error {
+        match &e {
+            kube::Error::Api(r) if r.code == 401 || r.code == 403 => Error::Auth(e.to_string()),
+            kube::Error::Api(r) if r.code == 404 => Error::NotFound(e.to_string()),
+            kube::Error::Auth(_) => Error::Auth(e.to_string()),
+            kube::Error::SerdeError(_) => Error::Parse(e.to_string()),
+            _ => Error::Api(e.to_string()),
+        }
+    }
+}
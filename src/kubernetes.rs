@@ -1,474 +1,2063 @@
-use std::{str::FromStr};
+use std::{borrow::Cow, str::FromStr, collections::BTreeMap, time::Duration};
 
-use kube::{Client, Api, api::ListParams, core::ObjectMeta};
-use k8s_openapi::{api::core::v1::{Node, Pod, Namespace, Container}, apimachinery::pkg::api::resource::Quantity};
+use kube::{api::ListParams, core::ObjectMeta};
+use k8s_openapi::{api::core::v1::{Node, Pod, PodSpec, Container}, apimachinery::pkg::api::resource::Quantity, chrono::{DateTime, Utc}};
 use tabled::{Tabled};
 
 use crate::utils::{parse_cpu_requests, parse_capacity_requests};
 
-use super::utils;
+use super::{error::Error, utils, scheduler, source::DataSource};
 
 #[derive(serde::Deserialize, Clone, Debug)]
-struct Usage {
-    cpu: Quantity,
-    memory: Quantity,
+pub struct Usage {
+    pub cpu: Quantity,
+    pub memory: Quantity,
+    /// only served by the kubelet summary api
+    #[serde(rename = "ephemeral-storage", default)]
+    pub storage: Option<Quantity>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
-struct NodeMetrics {
-    metadata: ObjectMeta,
-    usage: Usage,
+pub struct NodeMetrics {
+    pub metadata: ObjectMeta,
+    pub usage: Usage,
+    /// name, used and capacity in Mi of the node filesystems, only served by the kubelet summary api
+    #[serde(skip)]
+    pub filesystems: Vec<(String, f32, f32)>,
+    /// pod, volume name, used and capacity in Mi of the pod volumes on the node, also only from the summary api
+    #[serde(skip)]
+    pub volumes: Vec<(String, String, f32, Option<f32>)>,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
-struct ContainerMetrics {
-    usage: Usage,
+pub struct ContainerMetrics {
+    pub name: String,
+    pub usage: Usage,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct PodMetrics {
+    pub metadata: ObjectMeta,
+    pub containers: Vec<ContainerMetrics>,
+    /// ephemeral storage of the whole pod including emptyDir volumes, only served by the kubelet summary api
+    #[serde(rename = "ephemeral-storage", default)]
+    pub storage: Option<Quantity>,
+}
+
+/// default version of the metrics api, the served one is found by discovery
+pub const METRICS_VERSION: &str = "v1beta1";
+
+impl kube::Resource for NodeMetrics {
+    type DynamicType = String;
+    type Scope = k8s_openapi::ClusterResourceScope;
+
+    fn kind(_: &String) -> Cow<'_, str> {
+        "NodeMetrics".into()
+    }
+
+    fn group(_: &String) -> Cow<'_, str> {
+        "metrics.k8s.io".into()
+    }
+
+    fn version(version: &String) -> Cow<'_, str> {
+        version.into()
+    }
+
+    fn plural(_: &String) -> Cow<'_, str> {
+        "nodes".into()
+    }
+
+    fn meta(&self) -> &ObjectMeta {
+        &self.metadata
+    }
+
+    fn meta_mut(&mut self) -> &mut ObjectMeta {
+        &mut self.metadata
+    }
+}
+
+impl kube::Resource for PodMetrics {
+    type DynamicType = String;
+    // dynamic so the api can be scoped to a namespace with the served version
+    type Scope = kube::core::DynamicResourceScope;
+
+    fn kind(_: &String) -> Cow<'_, str> {
+        "PodMetrics".into()
+    }
+
+    fn group(_: &String) -> Cow<'_, str> {
+        "metrics.k8s.io".into()
+    }
+
+    fn version(version: &String) -> Cow<'_, str> {
+        version.into()
+    }
+
+    fn plural(_: &String) -> Cow<'_, str> {
+        "pods".into()
+    }
+
+    fn meta(&self) -> &ObjectMeta {
+        &self.metadata
+    }
+
+    fn meta_mut(&mut self) -> &mut ObjectMeta {
+        &mut self.metadata
+    }
+}
+
+#[derive(Clone)]
+pub struct ResouceRequests {
+    pub name: String,
+    pub cpu_requests: u32,
+    pub cpu_total: u32,
+    pub cpu_usage: Option<u32>,
+    pub mem_requests: f32,
+    pub mem_total: f32,
+    pub mem_usage: Option<f32>,
+    pub storage_requests: f32,
+    pub storage_total: f32,
+    pub storage_usage: Option<f32>,
+    pub pods: usize,
+    pub pods_total: usize,
+}
+
+#[derive(Clone, Default)]
+pub struct ContainerResources {
+    pub cpu_requests: u32,
+    pub cpu_limits: u32,
+    pub cpu_usage: Option<u32>,
+    pub mem_requests: f32,
+    pub mem_limits: f32,
+    pub mem_usage: Option<f32>,
+}
+
+/// requests and limits of one container of a workload with the usage samples of all its running replicas
+#[derive(Default)]
+pub struct ContainerUsage {
+    pub namespace: String,
+    pub workload: String,
+    pub container: String,
+    /// an init container, it runs to completion before the app containers start
+    pub init: bool,
+    pub replicas: usize,
+    pub qos: String,
+    pub resources: ContainerResources,
+    /// requests and limits the container doesn't declare, eg: cpu limits
+    pub missing: Vec<&'static str>,
+    pub cpu: Vec<u32>,
+    pub mem: Vec<f32>,
+    /// highest usage over the prometheus window, when the source has one
+    pub cpu_peak: Option<u32>,
+    pub mem_peak: Option<f32>,
+}
+
+/// requests, usage samples and the time of the last pod start, restart or creation of a namespace
+#[derive(Default)]
+pub struct NamespaceActivity {
+    pub name: String,
+    pub requests: (u32, f32, f32, usize),
+    pub cpu: Vec<u32>,
+    pub mem: Vec<f32>,
+    pub last_activity: Option<DateTime<Utc>>,
+}
+
+/// a scheduled pod holding its requests while some of its containers aren't ready
+pub struct StuckPod {
+    pub namespace: String,
+    pub name: String,
+    pub node: String,
+    pub cpu_requests: u32,
+    pub mem_requests: f32,
+    pub restarts: i32,
+    pub reasons: Vec<String>,
+}
+
+/// memory limit and working set of a container of a running pod, in Mi
+pub struct ContainerMemory {
+    pub namespace: String,
+    pub pod: String,
+    pub container: String,
+    pub node: String,
+    pub limit: f32,
+    pub usage: f32,
+}
+
+/// an OOMKilled container termination or an eviction of a pod
+pub struct PressureEvent {
+    pub namespace: String,
+    pub pod: String,
+    pub container: Option<String>,
+    pub reason: String,
+    pub time: Option<DateTime<Utc>>,
+    pub message: String,
+}
+
+/// pressure conditions, memory requests and usage and the recent OOM kills and evictions of a node
+pub struct NodePressure {
+    pub name: String,
+    pub conditions: Vec<(String, String)>,
+    pub mem_allocatable: f32,
+    pub mem_requests: f32,
+    pub mem_usage: Option<f32>,
+    pub events: Vec<PressureEvent>,
+}
+
+pub type PodTree = BTreeMap<String, Vec<(String, ContainerResources)>>;
+
+#[derive(Clone, Default)]
+pub struct NodeTree {
+    pub cpu_total: u32,
+    pub mem_total: f32,
+    pub namespaces: BTreeMap<String, PodTree>,
+}
+
+#[derive(Tabled)]
+pub struct TreeStatus {
+    name: String,
+    cpu: String,
+    #[tabled(rename = "cpu limits")]
+    cpu_limits: String,
+    #[tabled(rename = "cpu usage")]
+    cpu_usage: String,
+    mem: String,
+    #[tabled(rename = "mem limits")]
+    mem_limits: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+}
+
+pub struct PodResources {
+    pub namespace: String,
+    pub name: String,
+    pub resources: ContainerResources,
+}
+
+pub struct NodeDetail {
+    pub name: String,
+    pub capacity: (u32, f32, f32, usize),
+    pub allocatable: (u32, f32, f32, usize),
+    pub requests: (u32, f32, f32, usize),
+    pub usage: Option<(u32, f32, Option<f32>)>,
+    pub filesystems: Vec<(String, f32, f32)>,
+    pub volumes: Vec<(String, String, f32, Option<f32>)>,
+    pub conditions: Vec<(String, String)>,
+    pub taints: Vec<String>,
+    pub pods: Vec<PodResources>,
+}
+
+#[derive(Tabled)]
+pub struct NodeResourceStatus {
+    resource: String,
+    capacity: String,
+    allocatable: String,
+    requests: String,
+    limits: String,
+    usage: String,
+    free: String,
+}
+
+#[derive(Tabled)]
+pub struct VolumeStatus {
+    pod: String,
+    volume: String,
+    used: String,
+    capacity: String,
+}
+
+#[derive(Tabled)]
+pub struct PodStatus {
+    namespace: String,
+    name: String,
+    cpu: String,
+    #[tabled(rename = "cpu limits")]
+    cpu_limits: String,
+    #[tabled(rename = "cpu usage")]
+    cpu_usage: String,
+    mem: String,
+    #[tabled(rename = "mem limits")]
+    mem_limits: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+}
+
+#[derive(Tabled)]
+pub struct FitStatus {
+    node: String,
+    #[tabled(rename = "cpu free")]
+    cpu_free: String,
+    #[tabled(rename = "mem free")]
+    mem_free: String,
+    #[tabled(rename = "pods free")]
+    pods_free: String,
+    fits: String,
+    reason: String,
+}
+
+#[derive(Tabled)]
+pub struct FragmentationStatus {
+    group: String,
+    nodes: String,
+    #[tabled(rename = "cpu free")]
+    cpu_free: String,
+    #[tabled(rename = "largest cpu")]
+    cpu_largest: String,
+    #[tabled(rename = "mem free")]
+    mem_free: String,
+    #[tabled(rename = "largest mem")]
+    mem_largest: String,
+    fragmentation: String,
+}
+
+#[derive(Tabled)]
+pub struct PlacementStatus {
+    pod: String,
+    from: String,
+    to: String,
+    cpu: String,
+    mem: String,
+    reason: String,
+}
+
+#[derive(Tabled)]
+pub struct ConsolidationStatus {
+    state: String,
+    nodes: String,
+    cpu: String,
+    mem: String,
+}
+
+#[derive(Tabled)]
+pub struct InstanceTypeStatus {
+    #[tabled(rename = "instance type")]
+    instance_type: String,
+    #[tabled(rename = "replicas per node")]
+    per_node: String,
+    #[tabled(rename = "nodes needed")]
+    needed: String,
+}
+
+#[derive(Tabled)]
+pub struct WorkloadStatus {
+    workload: String,
+    pods: String,
+    cpu: String,
+    mem: String,
+    storage: String,
+}
+
+#[derive(Tabled)]
+pub struct ImpactStatus {
+    resource: String,
+    allocatable: String,
+    before: String,
+    added: String,
+    after: String,
+}
+
+#[derive(Default)]
+pub struct UsageSamples {
+    pub name: String,
+    pub cpu: Vec<u32>,
+    pub mem: Vec<f32>,
+}
+
+#[derive(Tabled)]
+pub struct UsageSampleStatus {
+    name: String,
+    samples: String,
+    #[tabled(rename = "cpu min")]
+    cpu_min: String,
+    #[tabled(rename = "cpu avg")]
+    cpu_avg: String,
+    #[tabled(rename = "cpu p95")]
+    cpu_p95: String,
+    #[tabled(rename = "cpu max")]
+    cpu_max: String,
+    #[tabled(rename = "mem min")]
+    mem_min: String,
+    #[tabled(rename = "mem avg")]
+    mem_avg: String,
+    #[tabled(rename = "mem p95")]
+    mem_p95: String,
+    #[tabled(rename = "mem max")]
+    mem_max: String,
+}
+
+#[derive(Tabled)]
+pub struct RightsizeStatus {
+    workload: String,
+    container: String,
+    replicas: String,
+    cpu: String,
+    #[tabled(rename = "cpu limits")]
+    cpu_limits: String,
+    mem: String,
+    #[tabled(rename = "mem limits")]
+    mem_limits: String,
+    #[tabled(rename = "cpu efficiency")]
+    cpu_efficiency: String,
+    #[tabled(rename = "mem efficiency")]
+    mem_efficiency: String,
+    #[tabled(rename = "cpu reclaimed")]
+    cpu_reclaimed: String,
+    #[tabled(rename = "mem reclaimed")]
+    mem_reclaimed: String,
+}
+
+#[derive(Tabled)]
+pub struct IdleNamespaceStatus {
+    namespace: String,
+    pods: String,
+    cpu: String,
+    mem: String,
+    #[tabled(rename = "max cpu usage")]
+    cpu_usage: String,
+    #[tabled(rename = "last activity")]
+    last_activity: String,
+    reason: String,
+}
+
+#[derive(Tabled)]
+pub struct StuckPodStatus {
+    namespace: String,
+    name: String,
+    node: String,
+    cpu: String,
+    mem: String,
+    restarts: String,
+    reason: String,
+}
+
+#[derive(Tabled)]
+pub struct StuckNamespaceStatus {
+    namespace: String,
+    pods: String,
+    cpu: String,
+    mem: String,
+    restarts: String,
+}
+
+#[derive(Tabled)]
+pub struct AuditStatus {
+    workload: String,
+    container: String,
+    replicas: String,
+    qos: String,
+    missing: String,
+    #[tabled(rename = "cpu usage")]
+    cpu_usage: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+}
+
+#[derive(Tabled)]
+pub struct OomRiskStatus {
+    namespace: String,
+    pod: String,
+    container: String,
+    node: String,
+    #[tabled(rename = "mem limits")]
+    mem_limits: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+}
+
+#[derive(Tabled)]
+pub struct NodeMemoryStatus {
+    node: String,
+    allocatable: String,
+    requests: String,
+    usage: String,
+}
+
+#[derive(Tabled)]
+pub struct NodePressureStatus {
+    node: String,
+    #[tabled(rename = "memory pressure")]
+    memory_pressure: String,
+    #[tabled(rename = "disk pressure")]
+    disk_pressure: String,
+    #[tabled(rename = "pid pressure")]
+    pid_pressure: String,
+    #[tabled(rename = "mem requests")]
+    mem_requests: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+    #[tabled(rename = "oom killed")]
+    oom_killed: String,
+    evicted: String,
+}
+
+#[derive(Tabled)]
+pub struct PressureEventStatus {
+    node: String,
+    pod: String,
+    container: String,
+    reason: String,
+    time: String,
+    message: String,
+}
+
+#[derive(Tabled)]
+pub struct ResourceStatus {
+    name: String,
+    cpu: String,
+    #[tabled(rename = "cpu usage")]
+    cpu_usage: String,
+    mem: String,
+    #[tabled(rename = "mem usage")]
+    mem_usage: String,
+    #[tabled(rename = "cpu efficiency")]
+    cpu_efficiency: String,
+    #[tabled(rename = "mem efficiency")]
+    mem_efficiency: String,
+    storage: String,
+    #[tabled(rename = "storage usage")]
+    storage_usage: String,
+    pods: String,
+    #[tabled(rename = "cpu free")]
+    cpu_free: String,
+    #[tabled(rename = "mem free")]
+    mem_free: String,
+    #[tabled(rename = "storage free")]
+    storage_free: String,
+    #[tabled(rename = "pods free")]
+    pods_free: String,
+}
+
+impl ResouceRequests {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String, cpu_requests: u32, cpu_total: u32, cpu_usage: Option<u32>,
+        mem_requests: f32, mem_total: f32, mem_usage: Option<f32>, storage_requests: f32,
+        storage_total: f32, storage_usage: Option<f32>, pods: usize, pods_total: usize) -> Self {
+        Self {
+            name,
+            cpu_requests,
+            cpu_total,
+            cpu_usage,
+            mem_requests,
+            mem_total,
+            mem_usage,
+            storage_requests,
+            storage_total,
+            storage_usage,
+            pods,
+            pods_total,
+        }
+    }
+}
+
+impl UsageSampleStatus {
+    pub fn new(name: String, samples: String, cpu: [String; 4], mem: [String; 4]) -> Self {
+        let [cpu_min, cpu_avg, cpu_p95, cpu_max] = cpu;
+        let [mem_min, mem_avg, mem_p95, mem_max] = mem;
+
+        Self {
+            name,
+            samples,
+            cpu_min,
+            cpu_avg,
+            cpu_p95,
+            cpu_max,
+            mem_min,
+            mem_avg,
+            mem_p95,
+            mem_max,
+        }
+    }
+}
+
+impl RightsizeStatus {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        workload: String, container: String, replicas: String, cpu: String, cpu_limits: String,
+        mem: String, mem_limits: String, cpu_efficiency: String, mem_efficiency: String,
+        cpu_reclaimed: String, mem_reclaimed: String) -> Self {
+        Self {
+            workload,
+            container,
+            replicas,
+            cpu,
+            cpu_limits,
+            mem,
+            mem_limits,
+            cpu_efficiency,
+            mem_efficiency,
+            cpu_reclaimed,
+            mem_reclaimed,
+        }
+    }
+}
+
+impl IdleNamespaceStatus {
+    pub fn new(namespace: String, pods: String, cpu: String, mem: String, cpu_usage: String, last_activity: String, reason: String) -> Self {
+        Self {
+            namespace,
+            pods,
+            cpu,
+            mem,
+            cpu_usage,
+            last_activity,
+            reason,
+        }
+    }
+}
+
+impl StuckPodStatus {
+    pub fn new(namespace: String, name: String, node: String, cpu: String, mem: String, restarts: String, reason: String) -> Self {
+        Self {
+            namespace,
+            name,
+            node,
+            cpu,
+            mem,
+            restarts,
+            reason,
+        }
+    }
+}
+
+impl StuckNamespaceStatus {
+    pub fn new(namespace: String, pods: String, cpu: String, mem: String, restarts: String) -> Self {
+        Self {
+            namespace,
+            pods,
+            cpu,
+            mem,
+            restarts,
+        }
+    }
+}
+
+impl AuditStatus {
+    pub fn new(workload: String, container: String, replicas: String, qos: String, missing: String, cpu_usage: String, mem_usage: String) -> Self {
+        Self {
+            workload,
+            container,
+            replicas,
+            qos,
+            missing,
+            cpu_usage,
+            mem_usage,
+        }
+    }
+}
+
+impl OomRiskStatus {
+    pub fn new(namespace: String, pod: String, container: String, node: String, mem_limits: String, mem_usage: String) -> Self {
+        Self {
+            namespace,
+            pod,
+            container,
+            node,
+            mem_limits,
+            mem_usage,
+        }
+    }
+}
+
+impl NodeMemoryStatus {
+    pub fn new(node: String, allocatable: String, requests: String, usage: String) -> Self {
+        Self {
+            node,
+            allocatable,
+            requests,
+            usage,
+        }
+    }
+}
+
+impl NodePressureStatus {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        node: String, memory_pressure: String, disk_pressure: String, pid_pressure: String,
+        mem_requests: String, mem_usage: String, oom_killed: String, evicted: String) -> Self {
+        Self {
+            node,
+            memory_pressure,
+            disk_pressure,
+            pid_pressure,
+            mem_requests,
+            mem_usage,
+            oom_killed,
+            evicted,
+        }
+    }
+}
+
+impl PressureEventStatus {
+    pub fn new(node: String, pod: String, container: String, reason: String, time: String, message: String) -> Self {
+        Self {
+            node,
+            pod,
+            container,
+            reason,
+            time,
+            message,
+        }
+    }
+}
+
+impl ResourceStatus {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String, cpu: String, cpu_usage: String, mem: String, mem_usage: String, cpu_efficiency: String,
+        mem_efficiency: String, storage: String, storage_usage: String, pods: String, cpu_free: String, mem_free: String, storage_free: String, pods_free: String) -> Self {
+        Self {
+            name,
+            cpu,
+            cpu_usage,
+            mem,
+            mem_usage,
+            cpu_efficiency,
+            mem_efficiency,
+            storage,
+            storage_usage,
+            pods,
+            cpu_free,
+            mem_free,
+            storage_free,
+            pods_free,
+        }
+    }
+}
+
+impl ContainerResources {
+    pub fn add(&mut self, other: &ContainerResources) {
+        self.cpu_requests += other.cpu_requests;
+        self.cpu_limits += other.cpu_limits;
+        self.cpu_usage = add_usage(self.cpu_usage, other.cpu_usage);
+        self.mem_requests += other.mem_requests;
+        self.mem_limits += other.mem_limits;
+        self.mem_usage = add_usage(self.mem_usage, other.mem_usage);
+    }
+}
+
+/// sums usage, which stays unknown only when neither side is known
+pub fn add_usage<T: std::ops::Add<Output = T> + Default>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (None, None) => None,
+        (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+    }
+}
+
+impl TreeStatus {
+    pub fn new(name: String, cpu: String, cpu_limits: String, cpu_usage: String, mem: String, mem_limits: String, mem_usage: String) -> Self {
+        Self {
+            name,
+            cpu,
+            cpu_limits,
+            cpu_usage,
+            mem,
+            mem_limits,
+            mem_usage,
+        }
+    }
+}
+
+impl NodeResourceStatus {
+    pub fn new(resource: String, capacity: String, allocatable: String, requests: String, limits: String, usage: String, free: String) -> Self {
+        Self {
+            resource,
+            capacity,
+            allocatable,
+            requests,
+            limits,
+            usage,
+            free,
+        }
+    }
+}
+
+impl FitStatus {
+    pub fn new(node: String, cpu_free: String, mem_free: String, pods_free: String, fits: String, reason: String) -> Self {
+        Self {
+            node,
+            cpu_free,
+            mem_free,
+            pods_free,
+            fits,
+            reason,
+        }
+    }
+}
+
+impl FragmentationStatus {
+    pub fn new(group: String, nodes: String, cpu_free: String, cpu_largest: String, mem_free: String, mem_largest: String, fragmentation: String) -> Self {
+        Self {
+            group,
+            nodes,
+            cpu_free,
+            cpu_largest,
+            mem_free,
+            mem_largest,
+            fragmentation,
+        }
+    }
+}
+
+impl PlacementStatus {
+    pub fn new(pod: String, from: String, to: String, cpu: String, mem: String, reason: String) -> Self {
+        Self {
+            pod,
+            from,
+            to,
+            cpu,
+            mem,
+            reason,
+        }
+    }
+}
+
+impl ConsolidationStatus {
+    pub fn new(state: String, nodes: &[scheduler::NodeState]) -> Self {
+        let (cpu_percentage, mem_percentage) = scheduler::get_requests_percentage(nodes);
+
+        Self {
+            state,
+            nodes: format!("{}", nodes.len()),
+            cpu: format!("{:.2}%", cpu_percentage),
+            mem: format!("{:.2}%", mem_percentage),
+        }
+    }
+}
+
+impl InstanceTypeStatus {
+    pub fn new(instance_type: String, per_node: String, needed: String) -> Self {
+        Self {
+            instance_type,
+            per_node,
+            needed,
+        }
+    }
+}
+
+impl WorkloadStatus {
+    pub fn new(workload: String, pods: String, cpu: String, mem: String, storage: String) -> Self {
+        Self {
+            workload,
+            pods,
+            cpu,
+            mem,
+            storage,
+        }
+    }
+}
+
+impl ImpactStatus {
+    pub fn new(resource: String, allocatable: String, before: String, added: String, after: String) -> Self {
+        Self {
+            resource,
+            allocatable,
+            before,
+            added,
+            after,
+        }
+    }
+}
+
+impl VolumeStatus {
+    pub fn new(pod: String, volume: String, used: String, capacity: String) -> Self {
+        Self {
+            pod,
+            volume,
+            used,
+            capacity,
+        }
+    }
+}
+
+impl PodStatus {
+    pub fn new(namespace: String, name: String, cr: &ContainerResources) -> Self {
+        Self {
+            namespace,
+            name,
+            cpu: format!("{}m", cr.cpu_requests),
+            cpu_limits: format!("{}m", cr.cpu_limits),
+            cpu_usage: utils::format_usage(cr.cpu_usage, |u| format!("{}m", u)),
+            mem: format!("{:.0}Mi", cr.mem_requests),
+            mem_limits: format!("{:.0}Mi", cr.mem_limits),
+            mem_usage: utils::format_usage(cr.mem_usage, |u| format!("{:.2}Mi", u)),
+        }
+    }
+}
+
+pub enum ResourceType {
+    Node,
+    Namespace,
+} 
+
+impl FromStr for ResourceType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "node" => Ok(ResourceType::Node),
+            "namespace" => Ok(ResourceType::Namespace),
+            _ => Err(format!("invalid resource type {}", s)),
+        }
+    }
+}
+
+/// cpu, memory and, when the source serves it, ephemeral storage usage
+pub async fn get_node_utilization(source: &impl DataSource, node_name: &str) -> Result<(u32, f32, Option<f32>), Error> {
+    let node_metrics = source.get_node_metrics(node_name).await.map_err(|e| e.context("Error getting node utilization information"))?;
+
+    parse_node_usage(&node_metrics)
+}
+
+fn parse_node_usage(node_metrics: &NodeMetrics) -> Result<(u32, f32, Option<f32>), Error> {
+    let cpu_usage = parse_cpu_requests(node_metrics.usage.cpu.0.to_string())?;
+    let mem_usage = parse_capacity_requests(node_metrics.usage.memory.0.to_string())?;
+    let storage_usage = node_metrics.usage.storage.as_ref().map(|s| parse_capacity_requests(s.0.to_string())).transpose()?;
+
+    Ok((cpu_usage, mem_usage, storage_usage))
+}
+
+/// usage of the running pods of a namespace, pods the metrics don't cover yet are left out
+pub async fn get_pod_utilization(source: &impl DataSource, namespace: &str) -> Result<(u32, f32, Option<f32>), Error> {
+    let lp = ListParams::default().fields(format!("metadata.namespace={}", namespace).as_str());
+
+    let pods: Vec<Pod> = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?.into_iter()
+        .filter(|p| p.spec.as_ref().and_then(|s| s.node_name.as_ref()).is_some())
+        .filter(|p| p.status.as_ref().and_then(|s| s.phase.as_deref()) == Some("Running"))
+        .collect();
+
+    if pods.is_empty() {
+        return Ok((0, 0.0, None));
+    }
+
+    let pod_metrics = source.list_pod_metrics(Some(namespace)).await.map_err(|e| e.context("Error getting pod utilization information"))?;
+
+    let mut cpu_usage: u32 = 0;
+    let mut mem_usage: f32 = 0.0;
+    let mut storage_usage: Option<f32> = None;
+    let mut measured = 0;
+
+    for pod in &pods {
+        let pod_metrics = match pod_metrics.iter().find(|m| m.metadata.namespace.as_deref() == Some(namespace) && m.metadata.name == pod.metadata.name) {
+            Some(p) => p,
+            None => continue,
+        };
+        measured += 1;
+
+        for container in &pod_metrics.containers {
+            cpu_usage += parse_cpu_requests(container.usage.cpu.0.to_string())?;
+            mem_usage += parse_capacity_requests(container.usage.memory.0.to_string())?;
+        }
+
+        storage_usage = add_usage(storage_usage, pod_metrics.storage.as_ref().map(|s| parse_capacity_requests(s.0.to_string())).transpose()?);
+    }
+
+    if measured == 0 {
+        return Err(Error::MetricsUnavailable(format!("Error getting pod utilization information, no metrics for the running pods of {}", namespace)));
+    }
+
+    Ok((cpu_usage, mem_usage, storage_usage))
+}
+
+pub async fn get_pods_resources_req(source: &impl DataSource, resource_type: &ResourceType, resource_name: &str) -> Result<(u32, f32, f32, usize), Error> {
+    let field_selector = match resource_type {
+        ResourceType::Node => format!("spec.nodeName={}", resource_name),
+        ResourceType::Namespace => format!("metadata.namespace={}", resource_name),
+    };
+
+    let lp = ListParams::default().fields(field_selector.as_str());
+
+    let pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut cpu_requested: u32 = 0;
+    let mut mem_requested: f32 = 0.0;
+    let mut storage_requested: f32 = 0.0;
+
+    for pod in pods.clone() {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        if let Some(spec) = pod.spec {
+            let (cpu_req, mem_req, storage_req) = get_pod_resources_req(spec).await?;
+
+            cpu_requested += cpu_req;
+            mem_requested += mem_req;
+            storage_requested += storage_req;
+        }
+    }
+
+    Ok((cpu_requested, mem_requested, storage_requested, pods.len()))
 }
 
-#[derive(serde::Deserialize, Clone, Debug)]
-struct PodMetrics {
-    metadata: ObjectMeta,
-    containers: Vec<ContainerMetrics>,
+pub(crate) fn is_pod_running(pod: &Pod) -> bool {
+    if let Some(status) = &pod.status {
+        if let Some(phase) = &status.phase {
+            if phase == "Failed" || phase == "Completed" || phase == "Succeeded" {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+pub(crate) async fn get_pod_resources_req(spec: PodSpec) -> Result<(u32, f32, f32), Error> {
+    let mut init_cpu_requested: u32 = 0;
+    let mut init_mem_requested: f32 = 0.0;
+    let mut init_storage_requested: f32 = 0.0;
+
+    if let Some(init_containers) = spec.init_containers {
+        (init_cpu_requested, init_mem_requested, init_storage_requested) = get_containers_resources_req(init_containers).await?;
+    }
+
+    let (cpu_req, mem_req, storage_req) = get_containers_resources_req(spec.containers).await?;
+
+    Ok((cpu_req.max(init_cpu_requested), mem_req.max(init_mem_requested), storage_req.max(init_storage_requested)))
+}
+
+async fn get_containers_resources_req(containers: Vec<Container>) -> Result<(u32, f32, f32), Error> {
+    let mut cpu_requested: u32 = 0;
+    let mut mem_requested: f32 = 0.0;
+    let mut storage_requested: f32 = 0.0;
+
+    for container in containers {
+        if let Some(resources) = container.resources {
+            if let Some(requests) = resources.requests {
+                let (cpu_req, mem_req, storage_req) = parse_resource_list(&requests)?;
+                cpu_requested += cpu_req;
+                mem_requested += mem_req;
+                storage_requested += storage_req;
+            }
+        }
+    }
+
+    Ok((cpu_requested, mem_requested, storage_requested))
+}
+
+fn parse_resource_list(resources: &BTreeMap<String, Quantity>) -> Result<(u32, f32, f32), Error> {
+    let mut cpu: u32 = 0;
+    let mut mem: f32 = 0.0;
+    let mut storage: f32 = 0.0;
+
+    if let Some(c) = resources.get("cpu") {
+        cpu = utils::parse_cpu_requests(c.0.to_string())?
+    }
+    if let Some(m) = resources.get("memory") {
+        mem = utils::parse_capacity_requests(m.0.to_string())?
+    }
+    if let Some(s) = resources.get("ephemeral-storage") {
+        storage = utils::parse_capacity_requests(s.0.to_string())?
+    }
+
+    Ok((cpu, mem, storage))
+}
+
+pub async fn get_cluster_node_info(source: &impl DataSource) -> Result<(u32, f32, f32, usize), Error> {
+    let lp = ListParams::default();
+
+    let nodes = source.list_nodes(&lp).await.map_err(|e| e.context("Error getting nodes information"))?;
+
+    let mut cluster_total_cpu: u32 = 0;
+    let mut cluster_total_mem: f32 = 0.0;
+    let mut cluster_total_storage: f32 = 0.0;
+    let mut cluster_total_pods: usize =  0;
+
+    for node in nodes {
+        if let Some(node_name) = node.metadata.name {
+            let (total_cpu, total_mem, total_storage, total_pods) = get_node_info(source, &node_name).await?;
+            cluster_total_cpu += total_cpu;
+            cluster_total_mem += total_mem;
+            cluster_total_storage += total_storage;
+            cluster_total_pods += total_pods;
+        }
+    }
+
+    Ok((cluster_total_cpu, cluster_total_mem, cluster_total_storage, cluster_total_pods))
+}
+
+async fn get_node_info(source: &impl DataSource, node_name: &str) -> Result<(u32, f32, f32, usize), Error> {
+    let node = source.get_node(node_name).await.map_err(|e| e.context("Error get node information"))?;
+
+    get_node_allocatable(&node)
+}
+
+pub(crate) fn get_node_allocatable(node: &Node) -> Result<(u32, f32, f32, usize), Error> {
+    match node.status.as_ref().and_then(|s| s.allocatable.as_ref()) {
+        Some(allocatable) => parse_node_resources(allocatable)
+            .map_err(|e| e.context(&format!("Error parsing allocatable of node {}", node.metadata.name.as_deref().unwrap_or_default()))),
+        None => Ok((0, 0.0, 0.0, 0)),
+    }
+}
+
+fn get_node_capacity(node: &Node) -> Result<(u32, f32, f32, usize), Error> {
+    match node.status.as_ref().and_then(|s| s.capacity.as_ref()) {
+        Some(capacity) => parse_node_resources(capacity)
+            .map_err(|e| e.context(&format!("Error parsing capacity of node {}", node.metadata.name.as_deref().unwrap_or_default()))),
+        None => Ok((0, 0.0, 0.0, 0)),
+    }
+}
+
+fn parse_node_resources(resources: &BTreeMap<String, Quantity>) -> Result<(u32, f32, f32, usize), Error> {
+    let (total_cpu, total_mem, total_storage) = parse_resource_list(resources)?;
+    let mut total_pods: usize = 0;
+
+    if let Some(pods) = resources.get("pods") {
+        total_pods += pods.0.parse::<usize>().map_err(|_| Error::Parse(format!("invalid quantity {}", pods.0)))?
+    }
+
+    Ok((total_cpu, total_mem, total_storage, total_pods))
+}
+
+async fn list_nodes(source: &impl DataSource, selector: Option<String>) -> Result<Vec<Node>, Error> {
+    let mut lp = ListParams::default();
+
+    if let Some(node_labels) = selector {
+        lp = ListParams::default().labels(&node_labels)
+    }
+
+    source.list_nodes(&lp).await.map_err(|e| e.context("Error listing nodes"))
+}
+
+fn get_node_group(node: &Node, node_group: &Option<String>) -> String {
+    match node_group {
+        Some(label) => node.metadata.labels.as_ref()
+            .and_then(|labels| labels.get(label).cloned())
+            .unwrap_or_else(|| String::from("<none>")),
+        None => node.metadata.name.clone().unwrap_or_default(),
+    }
+}
+
+pub async fn collect_matrix(source: &impl DataSource, matrix: &mut BTreeMap<String, BTreeMap<String, (u32, f32)>>, selector: Option<String>, node_group: Option<String>) -> Result<(), Error> {
+    let nodes = list_nodes(source, selector).await?;
+
+    let mut node_groups: BTreeMap<String, String> = BTreeMap::new();
+    for node in nodes {
+        if let Some(node_name) = node.metadata.name.clone() {
+            node_groups.insert(node_name, get_node_group(&node, &node_group));
+        }
+    }
+
+    let pods = source.list_pods(&ListParams::default()).await.map_err(|e| e.context("Error listing pods"))?;
+
+    for pod in pods {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+
+        if let Some(spec) = pod.spec {
+            let group = match spec.node_name.as_ref().and_then(|n| node_groups.get(n)) {
+                Some(group) => group.clone(),
+                None => continue,
+            };
+
+            let (cpu_req, mem_req, _) = get_pod_resources_req(spec).await?;
+
+            let cell = matrix.entry(namespace).or_default().entry(group).or_insert((0, 0.0));
+            cell.0 += cpu_req;
+            cell.1 += mem_req;
+        }
+    }
+
+    Ok(())
+}
+
+async fn list_pod_metrics(source: &impl DataSource, errors: &mut Vec<Error>) -> BTreeMap<(String, String), Vec<ContainerMetrics>> {
+    let mut pod_metrics = BTreeMap::new();
+
+    match source.list_pod_metrics(None).await {
+        Ok(metrics) => {
+            for pm in metrics {
+                let namespace = pm.metadata.namespace.unwrap_or_default();
+                let name = pm.metadata.name.unwrap_or_default();
+                pod_metrics.insert((namespace, name), pm.containers);
+            }
+        },
+        Err(e) => errors.push(e.context("Error getting pod utilization information")),
+    }
+
+    pod_metrics
+}
+
+fn get_container_resources(container: &Container, metrics: Option<&Vec<ContainerMetrics>>) -> Result<ContainerResources, Error> {
+    let mut cr = ContainerResources::default();
+
+    if let Some(resources) = &container.resources {
+        if let Some(requests) = &resources.requests {
+            (cr.cpu_requests, cr.mem_requests, _) = parse_resource_list(requests)?;
+        }
+        if let Some(limits) = &resources.limits {
+            (cr.cpu_limits, cr.mem_limits, _) = parse_resource_list(limits)?;
+        }
+    }
+
+    if let Some(cm) = metrics.and_then(|m| m.iter().find(|cm| cm.name == container.name)) {
+        cr.cpu_usage = Some(parse_cpu_requests(cm.usage.cpu.0.to_string())?);
+        cr.mem_usage = Some(parse_capacity_requests(cm.usage.memory.0.to_string())?);
+    }
+
+    Ok(cr)
+}
+
+pub async fn collect_tree(source: &impl DataSource, tree: &mut BTreeMap<String, NodeTree>, utilization: bool, selector: Option<String>, errors: &mut Vec<Error>) -> Result<(), Error> {
+    let nodes = list_nodes(source, selector).await?;
+
+    for node in nodes {
+        if let Some(node_name) = node.metadata.name {
+            let (cpu_total, mem_total, _, _) = get_node_info(source, &node_name).await?;
+            tree.insert(node_name, NodeTree { cpu_total, mem_total, ..Default::default() });
+        }
+    }
+
+    let pods = source.list_pods(&ListParams::default()).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut pod_metrics = BTreeMap::new();
+    if utilization {
+        match source.metrics_available() {
+            Ok(()) => pod_metrics = list_pod_metrics(source, errors).await,
+            Err(e) => errors.push(e),
+        }
+    }
+
+    for pod in pods {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        let namespace = pod.metadata.namespace.unwrap_or_default();
+        let pod_name = pod.metadata.name.unwrap_or_default();
+
+        if let Some(spec) = pod.spec {
+            let node_tree = match spec.node_name.as_ref().and_then(|n| tree.get_mut(n)) {
+                Some(node_tree) => node_tree,
+                None => continue,
+            };
+
+            let metrics = pod_metrics.get(&(namespace.clone(), pod_name.clone()));
+            let containers = spec.containers.iter()
+                .map(|c| get_container_resources(c, metrics).map(|r| (c.name.clone(), r)))
+                .collect::<Result<_, Error>>()?;
+
+            node_tree.namespaces.entry(namespace).or_default().insert(pod_name, containers);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn get_node_detail(source: &impl DataSource, node_name: &str, utilization: bool, errors: &mut Vec<Error>) -> Result<NodeDetail, Error> {
+    let node = source.get_node(node_name).await.map_err(|e| e.context("Error get node information"))?;
+
+    let mut conditions = Vec::new();
+    if let Some(node_conditions) = node.status.as_ref().and_then(|s| s.conditions.as_ref()) {
+        for condition in node_conditions {
+            conditions.push((condition.type_.clone(), condition.status.clone()));
+        }
+    }
+
+    let mut taints = Vec::new();
+    if let Some(node_taints) = node.spec.as_ref().and_then(|s| s.taints.as_ref()) {
+        for taint in node_taints {
+            taints.push(scheduler::format_taint(taint));
+        }
+    }
+
+    let requests = get_pods_resources_req(source, &ResourceType::Node, node_name).await?;
+
+    let mut usage = None;
+    let mut filesystems = Vec::new();
+    let mut volumes = Vec::new();
+    let mut pod_metrics = BTreeMap::new();
+    if utilization {
+        match source.metrics_available() {
+            Ok(()) => {
+                match source.get_node_metrics(node_name).await {
+                    Ok(m) => {
+                        usage = Some(parse_node_usage(&m)?);
+                        filesystems = m.filesystems;
+                        volumes = m.volumes;
+                    },
+                    Err(e) => errors.push(e.context("Error getting node utilization information")),
+                }
+                pod_metrics = list_pod_metrics(source, errors).await;
+            },
+            Err(e) => errors.push(e),
+        }
+    }
+
+    let lp = ListParams::default().fields(format!("spec.nodeName={}", node_name).as_str());
+
+    let node_pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut pods = Vec::new();
+    for pod in node_pods {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        let namespace = pod.metadata.namespace.unwrap_or_default();
+        let name = pod.metadata.name.unwrap_or_default();
+
+        if let Some(spec) = pod.spec {
+            let metrics = pod_metrics.get(&(namespace.clone(), name.clone()));
+            let mut resources = ContainerResources::default();
+            for container in &spec.containers {
+                resources.add(&get_container_resources(container, metrics)?);
+            }
+
+            pods.push(PodResources { namespace, name, resources });
+        }
+    }
+
+    Ok(NodeDetail {
+        name: node_name.to_string(),
+        capacity: get_node_capacity(&node)?,
+        allocatable: get_node_allocatable(&node)?,
+        requests,
+        usage,
+        filesystems,
+        volumes,
+        conditions,
+        taints,
+        pods,
+    })
+}
+
+pub async fn get_pod_shape(name: String, spec: PodSpec) -> Result<scheduler::PodShape, Error> {
+    let tolerations = spec.tolerations.clone().unwrap_or_default();
+    let node_selector = spec.node_selector.clone().unwrap_or_default();
+    let node_affinity = spec.affinity.as_ref()
+        .and_then(|a| a.node_affinity.as_ref())
+        .and_then(|a| a.required_during_scheduling_ignored_during_execution.clone());
+
+    let (cpu_requests, mem_requests, storage_requests) = get_pod_resources_req(spec).await?;
+
+    Ok(scheduler::PodShape {
+        name,
+        cpu_requests,
+        mem_requests,
+        storage_requests,
+        tolerations,
+        node_selector,
+        node_affinity,
+    })
+}
+
+pub async fn get_cluster_state(source: &impl DataSource, selector: Option<String>) -> Result<(Vec<scheduler::NodeState>, Vec<scheduler::ScheduledPod>), Error> {
+    let nodes = list_nodes(source, selector).await?;
+
+    let mut node_states: BTreeMap<String, scheduler::NodeState> = BTreeMap::new();
+    for node in nodes {
+        let name = node.metadata.name.clone().unwrap_or_default();
+        let spec = node.spec.clone().unwrap_or_default();
+
+        node_states.insert(name.clone(), scheduler::NodeState {
+            name,
+            labels: node.metadata.labels.clone().unwrap_or_default(),
+            taints: spec.taints.unwrap_or_default(),
+            unschedulable: spec.unschedulable.unwrap_or(false),
+            allocatable: get_node_allocatable(&node)?,
+            requests: (0, 0.0, 0.0, 0),
+        });
+    }
+
+    let pods = source.list_pods(&ListParams::default()).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut scheduled_pods = Vec::new();
+    for pod in pods {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        let daemonset = pod.metadata.owner_references.as_ref()
+            .map(|refs| refs.iter().any(|r| r.kind == "DaemonSet"))
+            .unwrap_or(false);
+        let mirror = pod.metadata.annotations.as_ref()
+            .map(|a| a.contains_key("kubernetes.io/config.mirror"))
+            .unwrap_or(false);
+        let name = format!("{}/{}", pod.metadata.namespace.unwrap_or_default(), pod.metadata.name.unwrap_or_default());
+
+        if let Some(spec) = pod.spec {
+            if let Some(node_state) = spec.node_name.as_ref().and_then(|n| node_states.get_mut(n)) {
+                let node = node_state.name.clone();
+                let shape = get_pod_shape(name, spec).await?;
+                node_state.place(&shape);
+
+                scheduled_pods.push(scheduler::ScheduledPod { node, daemonset, mirror, shape });
+            }
+        }
+    }
+
+    Ok((node_states.into_values().collect(), scheduled_pods))
+}
+
+pub async fn get_workload_template(source: &impl DataSource, workload: &str, namespace: &str) -> Result<(usize, PodSpec), Error> {
+    let (kind, name) = match workload.split_once('/') {
+        Some((kind, name)) => (kind.to_lowercase(), name),
+        None => return Err(Error::Parse(format!("invalid workload {}, expected <kind>/<name>", workload))),
+    };
+
+    let (replicas, template) = match kind.as_str() {
+        "deployment" | "deployments" | "deploy" => {
+            let spec = source.get_deployment(namespace, name).await
+                .map_err(|e| e.context("Error getting deployment"))?
+                .spec.unwrap_or_default();
+            (spec.replicas.unwrap_or(1), spec.template)
+        },
+        "statefulset" | "statefulsets" | "sts" => {
+            let spec = source.get_statefulset(namespace, name).await
+                .map_err(|e| e.context("Error getting statefulset"))?
+                .spec.unwrap_or_default();
+            (spec.replicas.unwrap_or(1), spec.template)
+        },
+        _ => return Err(Error::Parse(format!("invalid workload kind {}", kind))),
+    };
+
+    match template.spec {
+        Some(spec) => Ok((replicas.max(0) as usize, spec)),
+        None => Err(Error::Parse(format!("{} has no pod template", workload))),
+    }
+}
+
+async fn list_resource_names(source: &impl DataSource, resource_type: &ResourceType, selector: Option<String>) -> Result<Vec<String>, Error> {
+    let mut lp = ListParams::default();
+    let mut resource_names: Vec<String> = Vec::new();
+
+    if let Some(labels) = selector {
+        lp = ListParams::default().labels(&labels)
+    }
+
+    match resource_type {
+        ResourceType::Node => {
+            let nodes = source.list_nodes(&lp).await.map_err(|e| e.context("Error listing nodes"))?;
+
+            for node in nodes {
+                resource_names.push(node.metadata.name.ok_or_else(|| Error::Parse(String::from("node without a name")))?);
+            }
+
+        },
+        ResourceType::Namespace => {
+            let namespaces = source.list_namespaces(&lp).await.map_err(|e| e.context("Error listing namespaces"))?;
+
+            for namespace in namespaces {
+                resource_names.push(namespace.metadata.name.ok_or_else(|| Error::Parse(String::from("namespace without a name")))?);
+            }
+        },
+    };
+
+    Ok(resource_names)
+}
+
+/// rows of the resource table, usage that can't be read is left unknown and reported in errors
+pub async fn get_resource_requests(source: &impl DataSource, resource_type: ResourceType, utilization: bool, selector: Option<String>, errors: &mut Vec<Error>) -> Result<Vec<ResouceRequests>, Error> {
+    let mut rrs = Vec::new();
+
+    let metrics = match utilization {
+        true => match source.metrics_available() {
+            Ok(()) => true,
+            Err(e) => {
+                errors.push(e);
+                false
+            }
+        },
+        false => false,
+    };
+
+    let resource_names = list_resource_names(source, &resource_type, selector).await?;
+
+    let mut cluster_cpu_req: u32 = 0;
+    let mut cluster_cpu_total: u32 = 0;
+    let mut cluster_cpu_usage: Option<u32> = None;
+    let mut cluster_mem_req: f32 = 0.0;
+    let mut cluster_mem_total: f32 = 0.0;
+    let mut cluster_mem_usage: Option<f32> = None;
+    let mut cluster_storage_req: f32 = 0.0;
+    let mut cluster_storage_total: f32 = 0.0;
+    let mut cluster_storage_usage: Option<f32> = None;
+    let mut cluster_pods_req: usize = 0;
+    let mut cluster_pods_total: usize = 0;
+
+    for name in resource_names {
+        let (cpu_requests, mem_requests, storage_requests, pods) = get_pods_resources_req(source, &resource_type, &name).await?;
+
+        let mut cpu_usage = None;
+        let mut mem_usage = None;
+        let mut storage_usage = None;
+
+        if metrics {
+            let usage = match &resource_type {
+                ResourceType::Node => get_node_utilization(source, &name).await,
+                ResourceType::Namespace => get_pod_utilization(source, &name).await,
+            };
+
+            match usage {
+                Ok((cpu, mem, storage)) => (cpu_usage, mem_usage, storage_usage) = (Some(cpu), Some(mem), storage),
+                Err(e) => errors.push(e),
+            }
+        }
+
+        cluster_cpu_req += cpu_requests;
+        cluster_cpu_usage = add_usage(cluster_cpu_usage, cpu_usage);
+        cluster_mem_req += mem_requests;
+        cluster_mem_usage = add_usage(cluster_mem_usage, mem_usage);
+        cluster_storage_req += storage_requests;
+        cluster_storage_usage = add_usage(cluster_storage_usage, storage_usage);
+        cluster_pods_req += pods;
+
+        let (cpu_total, mem_total, storage_total, pods_total) = match &resource_type {
+            ResourceType::Namespace => {
+                if (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total) == (0, 0.0, 0.0, 0) {
+                    (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total) = get_cluster_node_info(source).await?;
+                }
+                (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total)
+            },
+            ResourceType::Node => {
+                let (cpu_total, mem_total, storage_total, pods_total) = get_node_info(source, &name).await?;
+                cluster_cpu_total += cpu_total;
+                cluster_mem_total += mem_total;
+                cluster_storage_total += storage_total;
+                cluster_pods_total += pods_total;
+                (cpu_total, mem_total, storage_total, pods_total)
+            }
+        };
+
+        utils::add_data(name.clone(), cpu_requests, cpu_total, cpu_usage, mem_requests, mem_total, mem_usage, storage_requests, storage_total, storage_usage, pods, pods_total, &mut rrs).await;
+    }
+
+    utils::add_data(
+        String::from("*"),
+        cluster_cpu_req,
+        cluster_cpu_total,
+        cluster_cpu_usage,
+        cluster_mem_req,
+        cluster_mem_total,
+        cluster_mem_usage,
+        cluster_storage_req,
+        cluster_storage_total,
+        cluster_storage_usage,
+        cluster_pods_req,
+        cluster_pods_total,
+        &mut rrs
+    ).await;
+
+    Ok(rrs)
 }
 
-impl k8s_openapi::Resource for NodeMetrics {
-    const GROUP: &'static str = "metrics.k8s.io";
-    const KIND: &'static str = "node";
-    const VERSION: &'static str = "v1beta1";
-    const API_VERSION: &'static str = "metrics.k8s.io/v1beta1";
-    const URL_PATH_SEGMENT: &'static str = "nodes";
+/// usage of each row read `samples` times, `interval` apart, with the cluster total as the last row,
+/// a sample where any row failed is left out of the cluster total rather than summed from the rest
+pub async fn sample_resource_usage(source: &impl DataSource, resource_type: ResourceType, selector: Option<String>, samples: usize, interval: Duration, errors: &mut Vec<Error>) -> Result<Vec<UsageSamples>, Error> {
+    source.metrics_available()?;
 
-    type Scope = k8s_openapi::ClusterResourceScope;
-}
+    let resource_names = list_resource_names(source, &resource_type, selector).await?;
+    let mut rows: Vec<UsageSamples> = resource_names.iter()
+        .map(|name| UsageSamples { name: name.clone(), ..Default::default() })
+        .collect();
+    let mut cluster = UsageSamples { name: String::from("*"), ..Default::default() };
 
-impl k8s_openapi::Resource for PodMetrics {
-    const GROUP: &'static str = "metrics.k8s.io";
-    const KIND: &'static str = "pod";
-    const VERSION: &'static str = "v1beta1";
-    const API_VERSION: &'static str = "metrics.k8s.io/v1beta1";
-    const URL_PATH_SEGMENT: &'static str = "pods";
+    for i in 0..samples {
+        if i > 0 {
+            tokio::time::sleep(interval).await;
+        }
 
-    type Scope = k8s_openapi::NamespaceResourceScope;
-}
+        let mut cluster_cpu_usage = None;
+        let mut cluster_mem_usage = None;
+        let mut complete = true;
 
-impl k8s_openapi::Metadata for NodeMetrics {
-    type Ty = ObjectMeta;
+        for row in rows.iter_mut() {
+            let usage = match &resource_type {
+                ResourceType::Node => get_node_utilization(source, &row.name).await,
+                ResourceType::Namespace => get_pod_utilization(source, &row.name).await,
+            };
 
-    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
-        &self.metadata
-    }
+            match usage {
+                Ok((cpu, mem, _)) => {
+                    row.cpu.push(cpu);
+                    row.mem.push(mem);
+                    cluster_cpu_usage = add_usage(cluster_cpu_usage, Some(cpu));
+                    cluster_mem_usage = add_usage(cluster_mem_usage, Some(mem));
+                },
+                Err(e) => {
+                    complete = false;
+                    // the same error usually repeats on every sample
+                    if !errors.contains(&e) {
+                        errors.push(e);
+                    }
+                },
+            }
+        }
 
-    fn metadata_mut(&mut self) -> &mut<Self as k8s_openapi::Metadata>::Ty {
-        &mut self.metadata
+        if let (true, Some(cpu), Some(mem)) = (complete, cluster_cpu_usage, cluster_mem_usage) {
+            cluster.cpu.push(cpu);
+            cluster.mem.push(mem);
+        }
     }
+
+    rows.push(cluster);
+    Ok(rows)
 }
 
-impl k8s_openapi::Metadata for PodMetrics {
-    type Ty = ObjectMeta;
+/// kind/name of the controller owning the pod, deployments are found through the replicaset name
+pub fn get_workload_name(pod: &Pod) -> String {
+    let owner = pod.metadata.owner_references.as_ref().and_then(|refs| refs.iter().find(|r| r.controller == Some(true)).or(refs.first()));
 
-    fn metadata(&self) -> &<Self as k8s_openapi::Metadata>::Ty {
-        &self.metadata
+    match owner {
+        Some(owner) if owner.kind == "ReplicaSet" => {
+            let hash = pod.metadata.labels.as_ref().and_then(|l| l.get("pod-template-hash"));
+            match hash.and_then(|h| owner.name.strip_suffix(&format!("-{}", h))) {
+                Some(deployment) => format!("Deployment/{}", deployment),
+                None => format!("ReplicaSet/{}", owner.name),
+            }
+        },
+        Some(owner) => format!("{}/{}", owner.kind, owner.name),
+        None => format!("Pod/{}", pod.metadata.name.clone().unwrap_or_default()),
     }
+}
 
-    fn metadata_mut(&mut self) -> &mut<Self as k8s_openapi::Metadata>::Ty {
-        &mut self.metadata
+/// qos class from the pod status, or worked out from the requests and limits of its containers
+pub fn get_qos_class(pod: &Pod) -> Result<String, Error> {
+    if let Some(qos) = pod.status.as_ref().and_then(|s| s.qos_class.clone()) {
+        return Ok(qos);
     }
-}
 
-#[derive(Clone)]
-pub struct ResouceRequests {
-    pub name: String,
-    pub cpu_requests: u32,
-    pub cpu_total: u32,
-    pub cpu_usage: u32,
-    pub mem_requests: f32,
-    pub mem_total: f32,
-    pub mem_usage: f32,
-    pub storage_requests: f32,
-    pub storage_total: f32,
-    pub pods: usize,
-    pub pods_total: usize,
-}
+    let spec = match &pod.spec {
+        Some(spec) => spec,
+        None => return Ok(String::from("BestEffort")),
+    };
+    let containers: Vec<&Container> = spec.init_containers.iter().flatten().chain(spec.containers.iter()).collect();
 
-#[derive(Tabled)]
-pub struct ResourceStatus {
-    name: String,
-    cpu: String,
-    #[tabled(rename = "cpu usage")]
-    cpu_usage: String,
-    mem: String,
-    #[tabled(rename = "mem usage")]
-    mem_usage: String,
-    storage: String,
-    pods: String,
-}
+    let quantity = |key: &str, q: &Quantity| match key {
+        "cpu" => parse_cpu_requests(q.0.clone()).map(|c| c as f32),
+        _ => parse_capacity_requests(q.0.clone()),
+    };
 
-impl ResouceRequests {
-    pub fn new(
-        name: String, cpu_requests: u32, cpu_total: u32, cpu_usage: u32,
-        mem_requests: f32, mem_total: f32, mem_usage: f32, storage_requests: f32,
-        storage_total: f32, pods: usize, pods_total: usize) -> Self {
-        Self {
-            name,
-            cpu_requests,
-            cpu_total,
-            cpu_usage,
-            mem_requests,
-            mem_total,
-            mem_usage,
-            storage_requests,
-            storage_total,
-            pods,
-            pods_total,
+    let mut guaranteed = true;
+    for c in &containers {
+        let resources = c.resources.as_ref();
+        let requests = resources.and_then(|r| r.requests.as_ref());
+        let limits = resources.and_then(|r| r.limits.as_ref());
+
+        for key in ["cpu", "memory"] {
+            let limit = limits.and_then(|l| l.get(key));
+            // a request defaults to the limit when only the limit is set
+            let request = requests.and_then(|r| r.get(key)).or(limit);
+            guaranteed &= match (request, limit) {
+                (Some(r), Some(l)) => quantity(key, r)? == quantity(key, l)?,
+                _ => false,
+            };
         }
     }
-}
 
-impl ResourceStatus {
-    pub fn new(name: String, cpu: String, cpu_usage: String, mem: String, mem_usage: String, storage: String, pods: String) -> Self {
-        Self {
-            name,
-            cpu,
-            cpu_usage,
-            mem,
-            mem_usage,
-            storage,
-            pods,
-        }
+    if guaranteed {
+        Ok(String::from("Guaranteed"))
+    } else if containers.iter().all(|c| get_missing_resources(c).len() == 4) {
+        Ok(String::from("BestEffort"))
+    } else {
+        Ok(String::from("Burstable"))
     }
 }
 
-pub enum ResourceType {
-    Node,
-    Namespace,
-} 
-
-impl FromStr for ResourceType {
-    type Err = String;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "node" => Ok(ResourceType::Node),
-            "namespace" => Ok(ResourceType::Namespace),
-            _ => Err(format!("invalid resource type {}", s)),
+pub fn get_missing_resources(container: &Container) -> Vec<&'static str> {
+    let resources = container.resources.as_ref();
+    let requests = resources.and_then(|r| r.requests.as_ref());
+    let limits = resources.and_then(|r| r.limits.as_ref());
+
+    let mut missing = Vec::new();
+    for (name, list, key) in [
+        ("cpu requests", requests, "cpu"),
+        ("mem requests", requests, "memory"),
+        ("cpu limits", limits, "cpu"),
+        ("mem limits", limits, "memory"),
+    ] {
+        if !list.map(|l| l.contains_key(key)).unwrap_or(false) {
+            missing.push(name);
         }
     }
-}
-
-pub async fn get_node_utilization(client: Client, node_name: &String) -> (u32, f32) {
-    let api = Api::<NodeMetrics>::all(client);
-    let node_metrics = match api.get(node_name).await {
-        Ok(n) => n,
-        Err(e) => {
-            eprintln!("Error getting node utilization information {}", e);
-            return (0, 0.0);
-        }
-    };
-
-    let cpu_usage = parse_cpu_requests(node_metrics.usage.cpu.0.to_string());
-    let mem_usage = parse_capacity_requests(node_metrics.usage.memory.0.to_string());
 
-    return (cpu_usage, mem_usage);
+    missing
 }
 
-pub async fn get_pod_utilization(client: Client, namespace: &String) -> (u32, f32) {
-    let api = Api::<Pod>::namespaced(client.clone(), &namespace);
-    let lp = ListParams::default();
-
-    let pods = match api.list(&lp).await {
-        Ok(pods) => pods,
+/// per container requests, limits and usage of running pods, pod metrics are read `samples` times, `interval` apart
+pub async fn collect_container_usage(source: &impl DataSource, namespace: Option<String>, samples: usize, interval: Duration, errors: &mut Vec<Error>) -> Result<Vec<ContainerUsage>, Error> {
+    let metrics = match source.metrics_available() {
+        Ok(()) => true,
         Err(e) => {
-            eprintln!("Error listing pods {:?}", e);
-            return (0, 0.0);
+            errors.push(e);
+            false
         },
     };
 
-    let mut cpu_usage: u32 = 0;
-    let mut mem_usage: f32 = 0.0;
+    let mut lp = ListParams::default();
+    if let Some(namespace) = &namespace {
+        lp = lp.fields(&format!("metadata.namespace={}", namespace));
+    }
 
-    if pods.items.len() != 0 {
-        for pod in pods.items {
-            let api = Api::<PodMetrics>::namespaced(client.clone(), namespace);
-            let pod_metrics = match api.get(pod.metadata.name.unwrap().as_str()).await {
-                Ok(p) => p,
-                Err(e) => {
-                    eprintln!("Error getting pod utilization information {}", e);
-                    return (0, 0.0);
-                }
-            };
+    let pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
 
+    let mut containers: BTreeMap<(String, String, String), ContainerUsage> = BTreeMap::new();
+    let mut pod_workloads: BTreeMap<(String, String), String> = BTreeMap::new();
 
-            for container in pod_metrics.containers {
-                cpu_usage += parse_cpu_requests(container.usage.cpu.0.to_string());
-                mem_usage += parse_capacity_requests(container.usage.memory.0.to_string());
-            }
+    for pod in pods {
+        if !is_pod_running(&pod) {
+            continue;
         }
 
-        return (cpu_usage, mem_usage)
+        let workload = get_workload_name(&pod);
+        let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+        pod_workloads.insert((namespace.clone(), pod.metadata.name.clone().unwrap_or_default()), workload.clone());
+
+        let qos = get_qos_class(&pod)?;
+        let init_containers = pod.spec.iter().flat_map(|s| s.init_containers.iter().flatten()).map(|c| (c, true));
+        let app_containers = pod.spec.iter().flat_map(|s| s.containers.iter()).map(|c| (c, false));
+
+        for (container, init) in init_containers.chain(app_containers) {
+            let key = (namespace.clone(), workload.clone(), container.name.clone());
+            let resources = get_container_resources(container, None)?;
+            let cu = containers.entry(key).or_insert_with(|| ContainerUsage {
+                namespace: namespace.clone(),
+                workload: workload.clone(),
+                container: container.name.clone(),
+                init,
+                qos: qos.clone(),
+                resources,
+                missing: get_missing_resources(container),
+                ..Default::default()
+            });
+            cu.replicas += 1;
+        }
     }
 
-    return (0, 0.0)
-
-}
-
-pub async fn get_pods_resources_req(client: Client, resource_type: &ResourceType, resource_name: &String) -> (u32, f32, f32, usize) {
-    let api: Api<Pod> = Api::all(client);
-
-    let field_selector = match resource_type {
-        ResourceType::Node => format!("spec.nodeName={}", resource_name),
-        ResourceType::Namespace => format!("metadata.namespace={}", resource_name),
-    };
-
-    let lp = ListParams::default().fields(&field_selector.as_str());
-
-    let pods = match api.list(&lp).await {
-        Ok(pods) => pods,
-        Err(e) => {
-            eprintln!("Error listing pods {:?}", e);
-            return (0, 0.0, 0.0, 0);
+    for i in 0..samples {
+        if !metrics {
+            break;
+        }
+        if i > 0 {
+            tokio::time::sleep(interval).await;
         }
-    };
 
-    let mut cpu_requested: u32 = 0;
-    let mut mem_requested: f32 = 0.0;
-    let mut storage_requested: f32 = 0.0;
+        let mut sample_errors = Vec::new();
+        for ((namespace, name), metrics) in list_pod_metrics(source, &mut sample_errors).await {
+            let workload = match pod_workloads.get(&(namespace.clone(), name)) {
+                Some(w) => w.clone(),
+                None => continue,
+            };
 
-    for pod in pods.items.clone() {
-        if let Some(status) = pod.status {
-            if let Some(phase) = status.phase {
-                if phase == "Failed" || phase == "Completed" || phase == "Succeeded" {
-                    continue;
+            for cm in metrics {
+                if let Some(cu) = containers.get_mut(&(namespace.clone(), workload.clone(), cm.name)) {
+                    cu.cpu.push(parse_cpu_requests(cm.usage.cpu.0.to_string())?);
+                    cu.mem.push(parse_capacity_requests(cm.usage.memory.0.to_string())?);
                 }
             }
         }
 
-        if let Some(spec) = pod.spec {
-            let mut init_cpu_requested: u32 = 0;
-            let mut init_mem_requested: f32 = 0.0;
-            let mut init_storage_requested: f32 = 0.0;
-
-            if let Some(init_containers) = spec.init_containers {
-                (init_cpu_requested, init_mem_requested, init_storage_requested) = get_containers_resources_req(init_containers).await;
+        for e in sample_errors {
+            if !errors.contains(&e) {
+                errors.push(e);
             }
+        }
+    }
 
-            let (cpu_req, mem_req, storage_req) = get_containers_resources_req(spec.containers).await;
+    let peaks = match metrics {
+        true => source.list_pod_peak_metrics(namespace.as_deref()).await.unwrap_or_else(|e| {
+            if !errors.contains(&e) {
+                errors.push(e);
+            }
+            None
+        }),
+        false => None,
+    };
 
-            cpu_requested += cpu_req.max(init_cpu_requested);
-            mem_requested += mem_req.max(init_mem_requested);
-            storage_requested += storage_req.max(init_storage_requested);
+    for pm in peaks.unwrap_or_default() {
+        let namespace = pm.metadata.namespace.unwrap_or_default();
+        let workload = match pod_workloads.get(&(namespace.clone(), pm.metadata.name.unwrap_or_default())) {
+            Some(w) => w.clone(),
+            None => continue,
+        };
+
+        for cm in pm.containers {
+            if let Some(cu) = containers.get_mut(&(namespace.clone(), workload.clone(), cm.name)) {
+                let cpu = parse_cpu_requests(cm.usage.cpu.0.to_string())?;
+                let mem = parse_capacity_requests(cm.usage.memory.0.to_string())?;
+                cu.cpu_peak = Some(cu.cpu_peak.unwrap_or(0).max(cpu));
+                cu.mem_peak = Some(cu.mem_peak.unwrap_or(0.0).max(mem));
+            }
         }
     }
 
-    return (cpu_requested, mem_requested, storage_requested, pods.items.len());
+    Ok(containers.into_values().collect())
 }
 
-async fn get_containers_resources_req(containers: Vec<Container>) -> (u32, f32, f32) {
-    let mut cpu_requested: u32 = 0;
-    let mut mem_requested: f32 = 0.0;
-    let mut storage_requested: f32 = 0.0;
+/// latest of the pod creation, container starts and container terminations
+pub fn get_pod_last_activity(pod: &Pod) -> Option<DateTime<Utc>> {
+    let mut times: Vec<DateTime<Utc>> = pod.metadata.creation_timestamp.iter().map(|t| t.0).collect();
 
-    for container in containers {
-        if let Some(resources) = container.resources {
-            if let Some(requests) = resources.requests {
-                if let Some(cpu) = requests.get("cpu") {
-                    cpu_requested += utils::parse_cpu_requests(cpu.0.to_string())
-                }
-                if let Some(mem) = requests.get("memory") {
-                    mem_requested += utils::parse_capacity_requests(mem.0.to_string())
-                }
-                if let Some(storage) = requests.get("ephemeral-storage") {
-                    storage_requested += utils::parse_capacity_requests(storage.0.to_string())
-                }
-            }
+    for cs in pod.status.iter().flat_map(|s| s.container_statuses.iter().flatten()) {
+        if let Some(started) = cs.state.as_ref().and_then(|s| s.running.as_ref()).and_then(|r| r.started_at.as_ref()) {
+            times.push(started.0);
+        }
+        if let Some(finished) = cs.last_state.as_ref().and_then(|s| s.terminated.as_ref()).and_then(|t| t.finished_at.as_ref()) {
+            times.push(finished.0);
         }
     }
 
-    return (cpu_requested, mem_requested, storage_requested)
+    times.into_iter().max()
 }
 
-async fn get_cluster_node_info(client: Client) -> (u32, f32, f32, usize) {
-    let api: Api<Node> = Api::all(client.clone());
-    let lp = ListParams::default();
-
-    let nodes = match api.list(&lp).await {
-        Ok(nodes) => nodes,
+/// requests, usage and last activity of each namespace, usage is read `samples` times, `interval` apart
+pub async fn collect_namespace_activity(source: &impl DataSource, selector: Option<String>, samples: usize, interval: Duration, errors: &mut Vec<Error>) -> Result<Vec<NamespaceActivity>, Error> {
+    let metrics = match source.metrics_available() {
+        Ok(()) => true,
         Err(e) => {
-            eprintln!("Error getting nodes information {}", e);
-            return (0, 0.0, 0.0, 0);
-        }
+            errors.push(e);
+            false
+        },
     };
 
-    let mut cluster_total_cpu: u32 = 0;
-    let mut cluster_total_mem: f32 = 0.0;
-    let mut cluster_total_storage: f32 = 0.0;
-    let mut cluster_total_pods: usize =  0;
+    let mut namespaces = Vec::new();
+    for name in list_resource_names(source, &ResourceType::Namespace, selector).await? {
+        let requests = get_pods_resources_req(source, &ResourceType::Namespace, &name).await?;
 
-    for node in nodes {
-        if let Some(node_name) = node.metadata.name {
-            let (total_cpu, total_mem, total_storage, total_pods) = get_node_info(client.clone(), &node_name).await;
-            cluster_total_cpu += total_cpu;
-            cluster_total_mem += total_mem;
-            cluster_total_storage += total_storage;
-            cluster_total_pods += total_pods;
+        let lp = ListParams::default().fields(&format!("metadata.namespace={}", name));
+        let pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
+        let last_activity = pods.iter().filter_map(get_pod_last_activity).max();
+
+        namespaces.push(NamespaceActivity { name, requests, last_activity, ..Default::default() });
+    }
+
+    for i in 0..samples {
+        if !metrics {
+            break;
+        }
+        if i > 0 {
+            tokio::time::sleep(interval).await;
+        }
+
+        for namespace in namespaces.iter_mut() {
+            match get_pod_utilization(source, &namespace.name).await {
+                Ok((cpu, mem, _)) => {
+                    namespace.cpu.push(cpu);
+                    namespace.mem.push(mem);
+                },
+                Err(e) if !errors.contains(&e) => errors.push(e),
+                Err(_) => {},
+            }
         }
     }
 
-    return (cluster_total_cpu, cluster_total_mem, cluster_total_storage, cluster_total_pods)
+    Ok(namespaces)
 }
 
-async fn get_node_info(client: Client, node_name: &String) -> (u32, f32, f32, usize) {
-    let api: Api<Node> = Api::all(client);
-
-    let node = match api.get(node_name).await {
-        Ok(node) => node,
-        Err(e) => {
-            eprintln!("Error get node information {}", e);
-            return (0, 0.0, 0.0, 0);
-        }
+/// why containers of the pod aren't ready, eg: web CrashLoopBackOff, empty when all are ready
+pub fn get_stuck_reasons(pod: &Pod) -> Vec<String> {
+    let mut reasons = Vec::new();
+    let status = match &pod.status {
+        Some(status) => status,
+        None => return reasons,
     };
 
-    let mut total_cpu: u32 = 0;
-    let mut total_mem: f32 = 0.0;
-    let mut total_storage: f32 = 0.0;
-    let mut total_pods: usize =  0;
-
+    let init_statuses = status.init_container_statuses.iter().flatten().map(|cs| (cs, true));
+    let statuses = status.container_statuses.iter().flatten().map(|cs| (cs, false));
 
-    if let Some(node_status) = node.status {
-        if let Some(allocatable) = node_status.allocatable {
-            if let Some(cpu) = allocatable.get("cpu") {
-                total_cpu += utils::parse_cpu_requests(cpu.0.to_string())
-            }
-            if let Some(mem) = allocatable.get("memory") {
-                total_mem += utils::parse_capacity_requests(mem.0.to_string())
-            }
-            if let Some(storage) = allocatable.get("ephemeral-storage") {
-                total_storage += utils::parse_capacity_requests(storage.0.to_string())
-            }
-            if let Some(pods) = allocatable.get("pods") {
-                total_pods += pods.0.parse::<usize>().unwrap()
-            }
+    for (cs, init) in init_statuses.chain(statuses) {
+        if cs.ready {
+            continue;
         }
+
+        let state = cs.state.as_ref();
+        let reason = match (state.and_then(|s| s.waiting.as_ref()), state.and_then(|s| s.terminated.as_ref())) {
+            (Some(waiting), _) => waiting.reason.clone().unwrap_or_else(|| String::from("Waiting")),
+            // init containers that finished are expected to be not ready
+            (None, Some(terminated)) if init && terminated.exit_code == 0 => continue,
+            (None, Some(terminated)) => terminated.reason.clone().unwrap_or_else(|| String::from("Terminated")),
+            (None, None) => String::from("NotReady"),
+        };
+
+        reasons.push(format!("{} {}", cs.name, reason));
     }
 
-    return (total_cpu, total_mem, total_storage, total_pods)
+    reasons
 }
 
-pub async fn collect_info(client: Client, rrs: &mut Vec<ResouceRequests>, resource_type: ResourceType, utilization: bool, selector: Option<String>) {
+/// scheduled, running pods with containers that aren't ready, sorted by namespace
+pub async fn get_stuck_pods(source: &impl DataSource, namespace: Option<String>) -> Result<Vec<StuckPod>, Error> {
     let mut lp = ListParams::default();
-    let mut resource_names: Vec<String> = Vec::new();
+    if let Some(namespace) = &namespace {
+        lp = lp.fields(&format!("metadata.namespace={}", namespace));
+    }
 
-    match &resource_type {
-        ResourceType::Node => {
-            if let Some(node_lebels) = selector {
-                lp = ListParams::default().labels(&node_lebels)
-            }
+    let pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
 
-            let api: Api<Node> = Api::all(client.clone());
+    let mut stuck = Vec::new();
+    for pod in pods {
+        let node = pod.spec.as_ref().and_then(|s| s.node_name.clone());
+        let reasons = get_stuck_reasons(&pod);
 
-            let nodes = match api.list(&lp).await {
-                Ok(nodes) => nodes,
-                Err(e) => {
-                    eprintln!("Error listing nodes {:?}", e);
-                    return;
-                }
-            };
+        let node = match node {
+            Some(node) if is_pod_running(&pod) && !reasons.is_empty() => node,
+            _ => continue,
+        };
 
-            for node in nodes.items {
-                resource_names.push(node.metadata.name.unwrap());
-            }
+        let restarts = pod.status.iter()
+            .flat_map(|s| s.init_container_statuses.iter().flatten().chain(s.container_statuses.iter().flatten()))
+            .map(|cs| cs.restart_count)
+            .sum();
 
-        },
-        ResourceType::Namespace => {
-            if let Some(ns_labels) = selector {
-                lp = ListParams::default().labels(&ns_labels)
-            }
+        let (cpu_requests, mem_requests, _) = match pod.spec.clone() {
+            Some(spec) => get_pod_resources_req(spec).await?,
+            None => (0, 0.0, 0.0),
+        };
 
-            let api: Api<Namespace> = Api::all(client.clone());
+        stuck.push(StuckPod {
+            namespace: pod.metadata.namespace.unwrap_or_default(),
+            name: pod.metadata.name.unwrap_or_default(),
+            node,
+            cpu_requests,
+            mem_requests,
+            restarts,
+            reasons,
+        });
+    }
 
-            let namespaces = match api.list(&lp).await {
-                Ok(namespaces) => namespaces,
-                Err(e) => {
-                    eprintln!("Error listing namespaces {:?}", e);
-                    return;
-                }
-            };
+    stuck.sort_by(|a, b| (&a.namespace, &a.name).cmp(&(&b.namespace, &b.name)));
+    Ok(stuck)
+}
 
-            for namespace in namespaces.items {
-                resource_names.push(namespace.metadata.name.unwrap());
-            }
-        },
-    };
+/// memory limit and working set of every container with a memory limit and metrics
+pub async fn get_container_memory(source: &impl DataSource, namespace: Option<String>, errors: &mut Vec<Error>) -> Result<Vec<ContainerMemory>, Error> {
+    source.metrics_available()?;
 
-    let mut cluster_cpu_req: u32 = 0;
-    let mut cluster_cpu_total: u32 = 0;
-    let mut cluster_cpu_usage: u32 = 0;
-    let mut cluster_mem_req: f32 = 0.0;
-    let mut cluster_mem_total: f32 = 0.0;
-    let mut cluster_mem_usage: f32 = 0.0;
-    let mut cluster_storage_req: f32 = 0.0;
-    let mut cluster_storage_total: f32 = 0.0;
-    let mut cluster_pods_req: usize = 0;
-    let mut cluster_pods_total: usize = 0;
+    let mut lp = ListParams::default();
+    if let Some(namespace) = &namespace {
+        lp = lp.fields(&format!("metadata.namespace={}", namespace));
+    }
 
-    for name in resource_names {
-        let (cpu_requests, mem_requests, storage_requests, pods) = get_pods_resources_req(client.clone(), &resource_type, &name).await;
-
-        let mut cpu_usage: u32 = 0;
-        let mut mem_usage: f32 = 0.0;
-        let mut cpu_total: u32 = 0;
-        let mut mem_total: f32 = 0.0;
-        let mut storage_total: f32 = 0.0;
-        let mut pods_total: usize = 0;
-
-        if utilization {
-            match &resource_type {
-                ResourceType::Node => {
-                    (cpu_usage, mem_usage) = get_node_utilization(client.clone(), &name).await;
-                },
-                ResourceType::Namespace => {
-                    (cpu_usage, mem_usage) = get_pod_utilization(client.clone(), &name).await;
+    let pods = source.list_pods(&lp).await.map_err(|e| e.context("Error listing pods"))?;
+    let pod_metrics = list_pod_metrics(source, errors).await;
+
+    let mut containers = Vec::new();
+    for pod in pods {
+        if !is_pod_running(&pod) {
+            continue;
+        }
+
+        let namespace = pod.metadata.namespace.unwrap_or_default();
+        let name = pod.metadata.name.unwrap_or_default();
+        let metrics = pod_metrics.get(&(namespace.clone(), name.clone()));
+
+        if let Some(spec) = pod.spec {
+            let node = spec.node_name.clone().unwrap_or_default();
+
+            for container in &spec.containers {
+                let resources = get_container_resources(container, metrics)?;
+
+                if let (true, Some(usage)) = (resources.mem_limits > 0.0, resources.mem_usage) {
+                    containers.push(ContainerMemory {
+                        namespace: namespace.clone(),
+                        pod: name.clone(),
+                        container: container.name.clone(),
+                        node: node.clone(),
+                        limit: resources.mem_limits,
+                        usage,
+                    });
                 }
             }
         }
+    }
 
-        cluster_cpu_req += cpu_requests;
-        cluster_cpu_usage += cpu_usage;
-        cluster_mem_req += mem_requests;
-        cluster_mem_usage += mem_usage;
-        cluster_storage_req += storage_requests;
-        cluster_pods_req += pods;
+    Ok(containers)
+}
 
-        match &resource_type {
-            ResourceType::Namespace => {
-                if (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total) == (0, 0.0, 0.0, 0) {
-                    (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total) = get_cluster_node_info(client.clone()).await;
-                }
-                (cpu_total, mem_total, storage_total, pods_total) = (cluster_cpu_total, cluster_mem_total, cluster_storage_total, cluster_pods_total)
+/// OOMKilled terminations of the pod's containers and its eviction, from the pod status
+pub fn get_pressure_events(pod: &Pod) -> Vec<PressureEvent> {
+    let mut events = Vec::new();
+    let status = match &pod.status {
+        Some(status) => status,
+        None => return events,
+    };
+
+    let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+    let name = pod.metadata.name.clone().unwrap_or_default();
+
+    for cs in status.init_container_statuses.iter().flatten().chain(status.container_statuses.iter().flatten()) {
+        let terminations = [cs.state.as_ref(), cs.last_state.as_ref()].into_iter().flatten().filter_map(|s| s.terminated.as_ref());
+
+        for terminated in terminations.filter(|t| t.reason.as_deref() == Some("OOMKilled")) {
+            events.push(PressureEvent {
+                namespace: namespace.clone(),
+                pod: name.clone(),
+                container: Some(cs.name.clone()),
+                reason: String::from("OOMKilled"),
+                time: terminated.finished_at.as_ref().map(|t| t.0),
+                message: format!("exit code {}, {} restarts", terminated.exit_code, cs.restart_count),
+            });
+        }
+    }
+
+    if status.reason.as_deref() == Some("Evicted") {
+        // evicted pods keep no eviction time, the last condition change is the closest
+        let time = status.conditions.iter().flatten()
+            .filter_map(|c| c.last_transition_time.as_ref().map(|t| t.0))
+            .max()
+            .or_else(|| status.start_time.as_ref().map(|t| t.0));
+
+        events.push(PressureEvent {
+            namespace,
+            pod: name,
+            container: None,
+            reason: String::from("Evicted"),
+            time,
+            message: status.message.clone().unwrap_or_default(),
+        });
+    }
+
+    events
+}
+
+/// pressure of each node with the OOM kills and evictions since `since`, events without a time are kept
+pub async fn get_node_pressure(source: &impl DataSource, selector: Option<String>, utilization: bool, since: DateTime<Utc>, errors: &mut Vec<Error>) -> Result<Vec<NodePressure>, Error> {
+    let metrics = match utilization {
+        true => match source.metrics_available() {
+            Ok(()) => true,
+            Err(e) => {
+                errors.push(e);
+                false
             },
-            ResourceType::Node => {
-                (cpu_total, mem_total, storage_total, pods_total) = get_node_info(client.clone(), &name).await;
-                cluster_cpu_total += cpu_total;
-                cluster_mem_total += mem_total;
-                cluster_storage_total += storage_total;
-                cluster_pods_total += pods_total;
+        },
+        false => false,
+    };
+
+    let nodes = list_nodes(source, selector).await?;
+    let pods = source.list_pods(&ListParams::default()).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut pressure = Vec::new();
+    for node in nodes {
+        let name = node.metadata.name.clone().unwrap_or_default();
+
+        let conditions = node.status.as_ref().and_then(|s| s.conditions.as_ref()).iter().flat_map(|c| c.iter())
+            .filter(|c| matches!(c.type_.as_str(), "MemoryPressure" | "DiskPressure" | "PIDPressure"))
+            .map(|c| (c.type_.clone(), c.status.clone()))
+            .collect();
+
+        let (_, mem_requests, _, _) = get_pods_resources_req(source, &ResourceType::Node, &name).await?;
+        let (_, mem_allocatable, _, _) = get_node_allocatable(&node)?;
+
+        let mut mem_usage = None;
+        if metrics {
+            match get_node_utilization(source, &name).await {
+                Ok((_, mem, _)) => mem_usage = Some(mem),
+                Err(e) => errors.push(e),
             }
         }
 
-        utils::add_data(name.clone(), cpu_requests, cpu_total, cpu_usage, mem_requests, mem_total, mem_usage, storage_requests, storage_total, pods, pods_total, rrs).await;
+        let mut events: Vec<PressureEvent> = pods.iter()
+            .filter(|p| p.spec.as_ref().and_then(|s| s.node_name.as_deref()) == Some(name.as_str()))
+            .flat_map(get_pressure_events)
+            .filter(|e| e.time.map(|t| t >= since).unwrap_or(true))
+            .collect();
+        events.sort_by_key(|e| std::cmp::Reverse(e.time));
+
+        pressure.push(NodePressure { name, conditions, mem_allocatable, mem_requests, mem_usage, events });
     }
 
-    utils::add_data(
-        String::from("*"),
-        cluster_cpu_req,
-        cluster_cpu_total,
-        cluster_cpu_usage,
-        cluster_mem_req,
-        cluster_mem_total,
-        cluster_mem_usage,
-        cluster_storage_req,
-        cluster_storage_total,
-        cluster_pods_req,
-        cluster_pods_total,
-        rrs
-    ).await;
-}
\ No newline at end of file
+    Ok(pressure)
+}
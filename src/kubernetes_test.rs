@@ -0,0 +1,461 @@
+use super::{error::Error, kubernetes::*, snapshot::Snapshot};
+
+fn fixture() -> Snapshot {
+    let content = r#"
+kind: NodeList
+items:
+- metadata:
+    name: node-1
+  status:
+    allocatable: {cpu: "4", memory: 8Gi, ephemeral-storage: 10Gi, pods: "110"}
+- metadata:
+    name: node-2
+  status:
+    allocatable: {cpu: "2", memory: 4Gi, ephemeral-storage: 10Gi, pods: "110"}
+---
+kind: NamespaceList
+items:
+- metadata: {name: default}
+- metadata: {name: kube-system}
+---
+kind: PodList
+items:
+- metadata: {name: web, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: web
+      resources:
+        requests: {cpu: 500m, memory: 512Mi}
+  status: {phase: Running}
+- metadata: {name: done, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: done
+      resources:
+        requests: {cpu: "1", memory: 1Gi}
+  status: {phase: Succeeded}
+- metadata:
+    name: agent
+    namespace: kube-system
+    ownerReferences:
+    - {apiVersion: apps/v1, kind: DaemonSet, name: agent, uid: "1"}
+  spec:
+    nodeName: node-2
+    containers:
+    - name: agent
+      resources:
+        requests: {cpu: 100m, memory: 128Mi}
+  status: {phase: Running}
+---
+kind: NodeMetricsList
+items:
+- metadata: {name: node-1}
+  usage: {cpu: 1500m, memory: 2Gi}
+- metadata: {name: node-2}
+  usage: {cpu: 200m, memory: 1Gi}
+---
+kind: PodMetricsList
+items:
+- metadata: {name: web, namespace: default}
+  containers:
+  - name: web
+    usage: {cpu: 250m, memory: 300Mi}
+"#;
+
+    let mut snapshot = Snapshot::default();
+    snapshot.parse(content).unwrap();
+    snapshot
+}
+
+#[tokio::test]
+async fn test_get_resource_requests_node() {
+    let mut errors = Vec::new();
+    let rrs = get_resource_requests(&fixture(), ResourceType::Node, true, None, &mut errors).await.unwrap();
+    assert!(errors.is_empty());
+
+    let rows: Vec<_> = rrs.iter()
+        .map(|r| (r.name.as_str(), r.cpu_requests, r.cpu_total, r.cpu_usage, r.mem_requests, r.pods))
+        .collect();
+
+    assert_eq!(rows, vec![
+        ("node-1", 500, 4000, Some(1500), 512.0, 2),
+        ("node-2", 100, 2000, Some(200), 128.0, 1),
+        ("*", 600, 6000, Some(1700), 640.0, 3),
+    ]);
+}
+
+#[tokio::test]
+async fn test_get_resource_requests_namespace() {
+    let mut errors = Vec::new();
+    let rrs = get_resource_requests(&fixture(), ResourceType::Namespace, false, None, &mut errors).await.unwrap();
+    assert!(errors.is_empty());
+
+    let rows: Vec<_> = rrs.iter()
+        .map(|r| (r.name.as_str(), r.cpu_requests, r.cpu_total, r.mem_total))
+        .collect();
+
+    assert_eq!(rows, vec![
+        ("default", 500, 6000, 12288.0),
+        ("kube-system", 100, 6000, 12288.0),
+        ("*", 600, 6000, 12288.0),
+    ]);
+}
+
+#[tokio::test]
+async fn test_get_cluster_state() {
+    let (nodes, pods) = get_cluster_state(&fixture(), None).await.unwrap();
+
+    let nodes: Vec<_> = nodes.iter().map(|n| (n.name.as_str(), n.requests.0)).collect();
+    assert_eq!(nodes, vec![("node-1", 500), ("node-2", 100)]);
+
+    let pods: Vec<_> = pods.iter().map(|p| (p.shape.name.as_str(), p.node.as_str(), p.daemonset)).collect();
+    assert_eq!(pods, vec![("default/web", "node-1", false), ("kube-system/agent", "node-2", true)]);
+}
+
+#[tokio::test]
+async fn test_get_resource_requests_errors() {
+    let mut errors = Vec::new();
+    let rrs = get_resource_requests(&fixture(), ResourceType::Namespace, true, None, &mut errors).await.unwrap();
+
+    assert_eq!(rrs.len(), 3);
+    assert!(matches!(errors[..], [Error::MetricsUnavailable(_)]));
+
+    let mut source = fixture();
+    source.nodes.clear();
+
+    let result = get_node_detail(&source, "node-1", false, &mut errors).await;
+    assert!(matches!(result, Err(Error::NotFound(_))));
+}
+
+#[tokio::test]
+async fn test_get_pod_utilization() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: PodList
+items:
+- metadata: {name: pending, namespace: default}
+  spec:
+    containers:
+    - name: pending
+  status: {phase: Pending}
+- metadata: {name: starting, namespace: default}
+  spec:
+    nodeName: node-2
+    containers:
+    - name: starting
+  status: {phase: Running}
+"#).unwrap();
+
+    assert_eq!(get_pod_utilization(&source, "default").await, Ok((250, 300.0, None)));
+    assert!(matches!(get_pod_utilization(&source, "kube-system").await, Err(Error::MetricsUnavailable(_))));
+}
+
+#[tokio::test]
+async fn test_get_resource_requests_parse_errors() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: Namespace
+metadata: {}
+---
+kind: Node
+metadata: {name: node-3}
+status:
+  allocatable: {cpu: 4x, memory: 8Gi, pods: "110"}
+"#).unwrap();
+
+    let mut errors = Vec::new();
+    let result = get_resource_requests(&source, ResourceType::Node, false, None, &mut errors).await;
+    assert_eq!(result.err(), Some(Error::Parse(String::from("Error parsing allocatable of node node-3 invalid quantity 4x"))));
+
+    let result = get_resource_requests(&source, ResourceType::Namespace, false, None, &mut errors).await;
+    assert!(matches!(result, Err(Error::Parse(_))));
+}
+
+#[tokio::test]
+async fn test_sample_resource_usage() {
+    let mut errors = Vec::new();
+    let rows = sample_resource_usage(&fixture(), ResourceType::Node, None, 3, std::time::Duration::ZERO, &mut errors).await.unwrap();
+    assert!(errors.is_empty());
+
+    let rows: Vec<_> = rows.iter().map(|r| (r.name.as_str(), r.cpu.clone())).collect();
+    assert_eq!(rows, vec![
+        ("node-1", vec![1500, 1500, 1500]),
+        ("node-2", vec![200, 200, 200]),
+        ("*", vec![1700, 1700, 1700]),
+    ]);
+
+    // without usage for node-3 the cluster total would be low, those samples are left out
+    let mut source = fixture();
+    source.parse("kind: Node\nmetadata: {name: node-3}").unwrap();
+    let rows = sample_resource_usage(&source, ResourceType::Node, None, 2, std::time::Duration::ZERO, &mut errors).await.unwrap();
+    assert_eq!(errors.len(), 1);
+
+    let rows: Vec<_> = rows.iter().map(|r| (r.name.as_str(), r.cpu.clone())).collect();
+    assert_eq!(rows, vec![
+        ("node-1", vec![1500, 1500]),
+        ("node-2", vec![200, 200]),
+        ("node-3", vec![]),
+        ("*", vec![]),
+    ]);
+}
+
+#[tokio::test]
+async fn test_collect_container_usage() {
+    let mut errors = Vec::new();
+    let containers = collect_container_usage(&fixture(), None, 2, std::time::Duration::ZERO, &mut errors).await.unwrap();
+    assert!(errors.is_empty());
+
+    let rows: Vec<_> = containers.iter()
+        .map(|c| (c.namespace.as_str(), c.workload.as_str(), c.container.as_str(), c.replicas, c.resources.cpu_requests, c.cpu.clone()))
+        .collect();
+
+    assert_eq!(rows, vec![
+        ("default", "Pod/web", "web", 1, 500, vec![250, 250]),
+        ("kube-system", "DaemonSet/agent", "agent", 1, 100, vec![]),
+    ]);
+}
+
+#[tokio::test]
+async fn test_collect_namespace_activity() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: Pod
+metadata: {name: old, namespace: preview, creationTimestamp: "2023-01-01T00:00:00Z"}
+spec:
+  nodeName: node-1
+  containers:
+  - name: old
+    resources:
+      requests: {cpu: "2", memory: 4Gi}
+status:
+  phase: Running
+  containerStatuses:
+  - name: old
+    image: old
+    imageID: ""
+    ready: true
+    restartCount: 1
+    state: {running: {startedAt: "2023-01-02T00:00:00Z"}}
+    lastState: {terminated: {exitCode: 1, finishedAt: "2023-01-03T00:00:00Z"}}
+---
+kind: Namespace
+metadata: {name: preview}
+"#).unwrap();
+
+    let mut errors = Vec::new();
+    let namespaces = collect_namespace_activity(&source, None, 1, std::time::Duration::ZERO, &mut errors).await.unwrap();
+
+    let rows: Vec<_> = namespaces.iter()
+        .map(|n| (n.name.as_str(), n.requests.0, n.cpu.clone(), n.last_activity.map(|t| t.to_rfc3339())))
+        .collect();
+
+    assert_eq!(rows, vec![
+        ("default", 500, vec![250], None),
+        ("kube-system", 100, vec![], None),
+        ("preview", 2000, vec![], Some(String::from("2023-01-03T00:00:00+00:00"))),
+    ]);
+    assert!(matches!(errors[..], [Error::MetricsUnavailable(_), Error::MetricsUnavailable(_)]));
+}
+
+#[tokio::test]
+async fn test_get_stuck_pods() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: PodList
+items:
+- metadata: {name: api, namespace: shop}
+  spec:
+    nodeName: node-1
+    initContainers:
+    - name: migrate
+    containers:
+    - name: api
+      resources:
+        requests: {cpu: 500m, memory: 1Gi}
+    - name: proxy
+  status:
+    phase: Running
+    initContainerStatuses:
+    - {name: migrate, image: m, imageID: "", ready: false, restartCount: 0, state: {terminated: {exitCode: 0, reason: Completed}}}
+    containerStatuses:
+    - {name: api, image: api, imageID: "", ready: false, restartCount: 12, state: {waiting: {reason: CrashLoopBackOff}}}
+    - {name: proxy, image: p, imageID: "", ready: false, restartCount: 0, state: {waiting: {reason: ImagePullBackOff}}}
+- metadata: {name: pending, namespace: shop}
+  spec:
+    containers:
+    - name: pending
+  status:
+    phase: Pending
+"#).unwrap();
+
+    let pods = get_stuck_pods(&source, None).await.unwrap();
+    let pods: Vec<_> = pods.iter()
+        .map(|p| (p.namespace.as_str(), p.name.as_str(), p.cpu_requests, p.restarts, p.reasons.join(", ")))
+        .collect();
+
+    assert_eq!(pods, vec![("shop", "api", 500, 12, String::from("api CrashLoopBackOff, proxy ImagePullBackOff"))]);
+}
+
+#[tokio::test]
+async fn test_collect_container_usage_audit() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: PodList
+items:
+- metadata:
+    name: batch-5d8f-x1
+    namespace: jobs
+    labels: {pod-template-hash: 5d8f}
+    ownerReferences:
+    - {apiVersion: apps/v1, kind: ReplicaSet, name: batch-5d8f, uid: "2", controller: true}
+  spec:
+    containers:
+    - name: batch
+  status: {phase: Running}
+- metadata: {name: db, namespace: jobs}
+  spec:
+    containers:
+    - name: db
+      resources:
+        limits: {cpu: "1", memory: 1Gi}
+  status: {phase: Running}
+- metadata: {name: cache, namespace: jobs}
+  spec:
+    containers:
+    - name: cache
+      resources:
+        requests: {cpu: 1000m, memory: 1024Mi}
+        limits: {cpu: "1", memory: 1Gi}
+  status: {phase: Running}
+- metadata: {name: api, namespace: jobs}
+  spec:
+    initContainers:
+    - name: migrate
+    containers:
+    - name: api
+      resources:
+        requests: {cpu: 1000m, memory: 1024Mi}
+        limits: {cpu: "1", memory: 1Gi}
+  status: {phase: Running}
+"#).unwrap();
+
+    let mut errors = Vec::new();
+    let containers = collect_container_usage(&source, Some(String::from("jobs")), 0, std::time::Duration::ZERO, &mut errors).await.unwrap();
+
+    let rows: Vec<_> = containers.iter().map(|c| (c.workload.as_str(), c.container.as_str(), c.init, c.qos.as_str(), c.missing.clone())).collect();
+    assert_eq!(rows, vec![
+        ("Deployment/batch", "batch", false, "BestEffort", vec!["cpu requests", "mem requests", "cpu limits", "mem limits"]),
+        ("Pod/api", "api", false, "Burstable", vec![]),
+        ("Pod/api", "migrate", true, "Burstable", vec!["cpu requests", "mem requests", "cpu limits", "mem limits"]),
+        ("Pod/cache", "cache", false, "Guaranteed", vec![]),
+        ("Pod/db", "db", false, "Guaranteed", vec!["cpu requests", "mem requests"]),
+    ]);
+}
+
+#[tokio::test]
+async fn test_get_container_memory() {
+    let mut source = fixture();
+    source.parse(r#"
+kind: Pod
+metadata: {name: cache, namespace: default}
+spec:
+  nodeName: node-2
+  containers:
+  - name: redis
+    resources:
+      limits: {memory: 512Mi}
+  - name: exporter
+    resources:
+      limits: {memory: 64Mi}
+status: {phase: Running}
+---
+kind: PodMetrics
+metadata: {name: cache, namespace: default}
+containers:
+- name: redis
+  usage: {cpu: 10m, memory: 480Mi}
+"#).unwrap();
+
+    let mut errors = Vec::new();
+    let containers = get_container_memory(&source, None, &mut errors).await.unwrap();
+
+    let rows: Vec<_> = containers.iter().map(|c| (c.pod.as_str(), c.container.as_str(), c.node.as_str(), c.limit, c.usage)).collect();
+    assert_eq!(rows, vec![("cache", "redis", "node-2", 512.0, 480.0)]);
+}
+
+#[tokio::test]
+async fn test_get_node_pressure() {
+    let mut source = Snapshot::default();
+    source.parse(r#"
+kind: NodeList
+items:
+- metadata:
+    name: node-1
+  status:
+    allocatable: {cpu: "4", memory: 8Gi, ephemeral-storage: 10Gi, pods: "110"}
+    conditions:
+    - {type: Ready, status: "True"}
+    - {type: MemoryPressure, status: "True"}
+    - {type: DiskPressure, status: "False"}
+    - {type: PIDPressure, status: "False"}
+---
+kind: PodList
+items:
+- metadata: {name: cache, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: redis
+  status:
+    phase: Running
+    containerStatuses:
+    - name: redis
+      image: redis
+      imageID: ""
+      ready: true
+      restartCount: 3
+      state: {running: {}}
+      lastState: {terminated: {exitCode: 137, reason: OOMKilled, finishedAt: "2024-05-02T10:00:00Z"}}
+- metadata: {name: old, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: old
+  status:
+    phase: Running
+    containerStatuses:
+    - {name: old, image: old, imageID: "", ready: true, restartCount: 1, state: {running: {}}, lastState: {terminated: {exitCode: 137, reason: OOMKilled, finishedAt: "2024-04-01T10:00:00Z"}}}
+- metadata: {name: batch, namespace: jobs}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: batch
+  status:
+    phase: Failed
+    reason: Evicted
+    message: "The node was low on resource: memory."
+    startTime: "2024-05-01T08:00:00Z"
+---
+kind: NodeMetrics
+metadata: {name: node-1}
+usage: {cpu: 1500m, memory: 2Gi}
+"#).unwrap();
+
+    let since = "2024-05-01T00:00:00Z".parse().unwrap();
+    let mut errors = Vec::new();
+    let nodes = get_node_pressure(&source, None, true, since, &mut errors).await.unwrap();
+
+    assert!(errors.is_empty());
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].conditions, vec![
+        (String::from("MemoryPressure"), String::from("True")),
+        (String::from("DiskPressure"), String::from("False")),
+        (String::from("PIDPressure"), String::from("False")),
+    ]);
+    assert_eq!((nodes[0].mem_requests, nodes[0].mem_usage), (0.0, Some(2048.0)));
+
+    let events: Vec<_> = nodes[0].events.iter().map(|e| (e.pod.as_str(), e.container.as_deref(), e.reason.as_str())).collect();
+    assert_eq!(events, vec![("cache", Some("redis"), "OOMKilled"), ("batch", None, "Evicted")]);
+}
@@ -0,0 +1,31 @@
+//! resource requests, capacity and usage aggregation used by the `kubectl-resource_status` plugin.
+
+pub mod error;
+pub mod utils;
+pub mod kubernetes;
+pub mod scheduler;
+pub mod manifest;
+pub mod model;
+pub mod snapshot;
+pub mod prometheus;
+pub mod source;
+pub mod summary;
+
+#[cfg(test)]
+mod utils_test;
+#[cfg(test)]
+mod scheduler_test;
+#[cfg(test)]
+mod manifest_test;
+#[cfg(test)]
+mod kubernetes_test;
+#[cfg(test)]
+mod snapshot_test;
+#[cfg(test)]
+mod model_test;
+#[cfg(test)]
+mod summary_test;
+#[cfg(test)]
+mod prometheus_test;
+#[cfg(test)]
+mod source_test;
@@ -1,8 +1,10 @@
-use std::str::FromStr;
+use std::{str::FromStr, collections::BTreeMap, sync::Arc, process::ExitCode};
 use kube::{Client};
+use k8s_openapi::{api::core::v1::Pod, chrono::Utc};
 
 use bpaf::Bpaf;
-use tabled::{Table, Style, Disable, locator::ByColumnName};
+use resource_status::{error::Error, kubernetes, manifest, prometheus, scheduler, snapshot, source::{self, DataSource}, utils};
+use tabled::{Table, Style, Disable, locator::ByColumnName, builder::Builder};
 
 #[derive(Clone, Debug, Bpaf)]
 #[bpaf(options, version)]
@@ -18,53 +20,406 @@ struct Options {
     /// filter based on resource type (eg: node, namespace), default: node
     resource_type: Option<String>,
     #[bpaf(short('s'), long)]
-    /// filter by cpu, mem, storage or pods
-    sort_by: Option<String>
+    /// sort by cpu, mem, storage, pods, frag or eff (usage / requests, lowest first)
+    sort_by: Option<String>,
+    #[bpaf(short('m'), long)]
+    /// show cpu or mem requests per namespace and node
+    matrix: Option<String>,
+    #[bpaf(short('g'), long)]
+    /// group nodes by label (eg: node.kubernetes.io/instance-type)
+    node_group: Option<String>,
+    #[bpaf(long)]
+    /// show requests, limits and usage per node, namespace, pod and container
+    tree: bool,
+    #[bpaf(short('f'), long)]
+    /// show free resources per node
+    free: bool,
+    #[bpaf(long, argument("PATH"))]
+    /// read nodes, pods, namespaces and metrics from kubectl -o json/yaml dumps instead of the cluster
+    from: Option<String>,
+    #[bpaf(long, argument("SOURCE"))]
+    /// read usage from metrics-server, kubelet (stats/summary) or prometheus, default: metrics-server
+    metrics_source: Option<String>,
+    #[bpaf(long, argument("URL"))]
+    /// prometheus url for --metrics-source prometheus (eg: http://localhost:9090)
+    prometheus_url: Option<String>,
+    #[bpaf(long, argument("DURATION"))]
+    /// prometheus usage window (eg: 7d), default: current usage
+    window: Option<String>,
+    #[bpaf(long, argument("QUANTILE"))]
+    /// quantile of prometheus usage over the window, default: 0.95
+    quantile: Option<f64>,
+    #[bpaf(long, argument("N"))]
+    /// read usage N times and show min, avg, p95 and max per row
+    samples: Option<usize>,
+    #[bpaf(long, argument("DURATION"))]
+    /// time between usage samples (eg: 10s, 1m), default: 10s
+    interval: Option<String>,
+    #[bpaf(external(command), optional)]
+    command: Option<Command>,
 }
 
-mod utils;
-mod kubernetes;
+#[derive(Clone, Debug, Bpaf)]
+enum Command {
+    #[bpaf(command)]
+    /// show detailed resource information of a single node
+    Node {
+        #[bpaf(long)]
+        /// cpu requests of a new pod to check the headroom for (eg: 500m)
+        cpu: Option<String>,
+        #[bpaf(long)]
+        /// memory requests of a new pod to check the headroom for (eg: 1Gi)
+        memory: Option<String>,
+        #[bpaf(positional("NODE"))]
+        name: String,
+    },
+    #[bpaf(command)]
+    /// check on which nodes a pod of the given size can be scheduled
+    Fit {
+        #[bpaf(long)]
+        /// pod manifest to check (yaml or json)
+        file: Option<String>,
+        #[bpaf(long)]
+        /// cpu requests of the pod (eg: 2)
+        cpu: Option<String>,
+        #[bpaf(long)]
+        /// memory requests of the pod (eg: 8Gi)
+        memory: Option<String>,
+    },
+    #[bpaf(command)]
+    /// show the largest schedulable pod and fragmentation per node group
+    Fragmentation,
+    #[bpaf(command("drain-sim"))]
+    /// simulate draining nodes and show which pods can't be rescheduled
+    DrainSim {
+        #[bpaf(positional("NODE"))]
+        nodes: Vec<String>,
+    },
+    #[bpaf(command)]
+    /// show which nodes could be removed while every pod still fits elsewhere
+    Consolidate,
+    #[bpaf(command)]
+    /// show the requests that manifests would add to the cluster
+    Impact {
+        #[bpaf(positional("FILE"))]
+        /// manifest files to read, default: stdin
+        files: Vec<String>,
+    },
+    #[bpaf(command)]
+    /// recommend container requests and limits from usage, use --samples or prometheus --window for more than one reading
+    Rightsize {
+        #[bpaf(short('n'), long)]
+        /// namespace of the workloads, default: all namespaces
+        namespace: Option<String>,
+        #[bpaf(long, argument("PERCENT"))]
+        /// headroom added on top of observed usage, default: 20
+        headroom: Option<f32>,
+    },
+    #[bpaf(command)]
+    /// list namespaces that hold requests but barely use cpu or had no pod activity for days
+    Idle {
+        #[bpaf(long, argument("MILLICORES"))]
+        /// max cpu usage of an idle namespace, default: 10
+        cpu_threshold: Option<u32>,
+        #[bpaf(long, argument("DAYS"))]
+        /// days without new pods or restarts of an idle namespace, default: 7
+        days: Option<i64>,
+    },
+    #[bpaf(command)]
+    /// show requests held by scheduled pods with containers that aren't ready, eg: CrashLoopBackOff
+    Stuck {
+        #[bpaf(short('n'), long)]
+        /// namespace of the pods, default: all namespaces
+        namespace: Option<String>,
+    },
+    #[bpaf(command)]
+    /// list containers missing cpu or memory requests or limits, with their usage when -u is set
+    Audit {
+        #[bpaf(short('n'), long)]
+        /// namespace of the workloads, default: all namespaces
+        namespace: Option<String>,
+        #[bpaf(long)]
+        /// only list containers missing requests
+        requests_only: bool,
+    },
+    #[bpaf(command("oom-risk"))]
+    /// list containers with a working set close to their memory limit and nodes with memory usage close to allocatable
+    OomRisk {
+        #[bpaf(short('n'), long)]
+        /// namespace of the pods, default: all namespaces
+        namespace: Option<String>,
+        #[bpaf(long, argument("PERCENT"))]
+        /// usage of the limit or allocatable to report, default: 90
+        threshold: Option<f32>,
+    },
+    #[bpaf(command)]
+    /// show recent OOM kills and evictions of each node next to its pressure conditions and memory
+    Pressure {
+        #[bpaf(long, argument("DURATION"))]
+        /// how far back to look for OOM kills and evictions, eg: 6h or 7d, default: 24h
+        since: Option<String>,
+    },
+    #[bpaf(command("what-if"))]
+    /// simulate changes to the cluster
+    WhatIf(#[bpaf(external(what_if))] WhatIf),
+}
 
-#[cfg(test)]
-mod utils_test;
+#[derive(Clone, Debug, Bpaf)]
+enum WhatIf {
+    #[bpaf(command)]
+    /// simulate scaling a deployment or statefulset
+    Scale {
+        #[bpaf(short('n'), long)]
+        /// namespace of the workload, default: default
+        namespace: Option<String>,
+        #[bpaf(long)]
+        /// number of replicas to scale to
+        replicas: usize,
+        #[bpaf(positional("KIND/NAME"))]
+        workload: String,
+    },
+}
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     let opts = options().run();
     let mut sort_by = utils::Filter::None;
     let mut resource_type = kubernetes::ResourceType::Node;
 
-    if let Some(rt) = opts.resource_type {
-        resource_type = match kubernetes::ResourceType::from_str(&rt) {
+    if let Some(rt) = &opts.resource_type {
+        resource_type = match kubernetes::ResourceType::from_str(rt) {
             Ok(rt) => rt,
             Err(e) => {
                 eprintln!("{}", e);
-                return;
+                return ExitCode::from(Error::Parse(e).exit_code());
             }
         };
     }
 
-    if let Some(s) = opts.sort_by {
-        sort_by = match utils::Filter::from_str(&s) {
+    if let Some(s) = &opts.sort_by {
+        sort_by = match utils::Filter::from_str(s) {
             Ok(s) => s,
             Err(e) => {
                 eprintln!("{}", e);
-                return;
+                return ExitCode::from(Error::Parse(e).exit_code());
             }
         }
     }
 
-    let mut resource_req = Vec::new();
-
-    let client = match Client::try_default().await {
+    let metrics_source = match get_metrics_source(&opts) {
+        Ok(ms) => ms,
         Err(e) => {
-            eprintln!("Error creating kubernetes client {:?}", e);
-            return;
-       },
-        Ok(client) => client,
+            eprintln!("{}", e);
+            return ExitCode::from(e.exit_code());
+        }
     };
 
-    kubernetes::collect_info(client.clone(), &mut resource_req, resource_type, opts.utilization, opts.selector).await;
+    let source = match &opts.from {
+        Some(path) => match snapshot::Snapshot::load(path) {
+            Ok(snapshot) => source::Source::Offline(Arc::new(snapshot)),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::from(e.exit_code());
+            }
+        },
+        None => match Client::try_default().await {
+            Err(e) => {
+                eprintln!("Error creating kubernetes client {:?}", e);
+                return ExitCode::from(Error::from(e).exit_code());
+           },
+            Ok(client) => source::Source::Live(source::ApiSource::new(client, metrics_source).await),
+        },
+    };
+
+    let mut errors = Vec::new();
+    let result = run(opts, &source, sort_by, resource_type, &mut errors).await;
+
+    for e in source.partial_errors() {
+        if !errors.contains(&e) {
+            errors.push(e);
+        }
+    }
+
+    for e in &errors {
+        eprintln!("{}", e);
+    }
+
+    // a fatal error decides the exit code, otherwise the first piece of missing data does
+    match result {
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(e.exit_code())
+        },
+        Ok(()) => match errors.first() {
+            Some(e) => ExitCode::from(e.exit_code()),
+            None => ExitCode::SUCCESS,
+        },
+    }
+}
+
+fn get_metrics_source(opts: &Options) -> Result<source::MetricsSource, Error> {
+    match opts.metrics_source.as_deref() {
+        None | Some("metrics-server") => Ok(source::MetricsSource::MetricsServer),
+        Some("kubelet") => Ok(source::MetricsSource::Kubelet),
+        Some("prometheus") => match &opts.prometheus_url {
+            Some(url) => Ok(source::MetricsSource::Prometheus(prometheus::Prometheus::new(url, opts.window.clone(), opts.quantile.unwrap_or(0.95))?)),
+            None => Err(Error::Parse(String::from("--prometheus-url is required with --metrics-source prometheus"))),
+        },
+        Some(ms) => Err(Error::Parse(format!("invalid metrics source {}", ms))),
+    }
+}
+
+async fn run(opts: Options, source: &source::Source, sort_by: utils::Filter, resource_type: kubernetes::ResourceType, errors: &mut Vec<Error>) -> Result<(), Error> {
+    match opts.command {
+        Some(Command::Node { name, cpu, memory }) => {
+            return print_node_detail(source, &name, cpu, memory, sort_by, opts.utilization, errors).await;
+        },
+        Some(Command::Fit { file, cpu, memory }) => {
+            return print_fit(source, file, cpu, memory, opts.selector).await;
+        },
+        Some(Command::Fragmentation) => {
+            let (nodes, _) = kubernetes::get_cluster_state(source, opts.selector).await?;
+
+            let mut table = Table::new(utils::parse_fragmentation_data(&nodes, &opts.node_group, sort_by));
+            table.with(Style::rounded());
+
+            println!("{}", table);
+            return Ok(());
+        },
+        Some(Command::DrainSim { nodes }) => {
+            return print_drain_sim(source, nodes, opts.selector).await;
+        },
+        Some(Command::Consolidate) => {
+            let (mut nodes, mut pods) = kubernetes::get_cluster_state(source, opts.selector).await?;
+            let before = kubernetes::ConsolidationStatus::new(String::from("before"), &nodes);
+            let removed = scheduler::consolidate(&mut nodes, &mut pods);
+            let after = kubernetes::ConsolidationStatus::new(String::from("after"), &nodes);
+
+            let mut table = Table::new(vec![before, after]);
+            table.with(Style::rounded());
+
+            println!("{}", table);
+            match removed.is_empty() {
+                true => println!("removable nodes: <none>"),
+                false => println!("removable nodes: {}", removed.join(", ")),
+            }
+            return Ok(());
+        },
+        Some(Command::WhatIf(WhatIf::Scale { namespace, replicas, workload })) => {
+            let namespace = namespace.unwrap_or_else(|| String::from("default"));
+            return print_what_if_scale(source, &workload, &namespace, replicas, opts.selector).await;
+        },
+        Some(Command::Impact { files }) => {
+            return print_impact(source, files, opts.selector).await;
+        },
+        Some(Command::Idle { cpu_threshold, days }) => {
+            let interval = utils::parse_duration(opts.interval.as_deref().unwrap_or("10s")).map_err(Error::Parse)?;
+            let namespaces = kubernetes::collect_namespace_activity(source, opts.selector, opts.samples.unwrap_or(1).max(1), interval, errors).await?;
+            let (rows, (cpu, mem)) = utils::parse_idle_data(&namespaces, cpu_threshold.unwrap_or(10), days.unwrap_or(7), Utc::now());
+
+            let mut table = Table::new(rows);
+            table.with(Style::rounded());
+
+            println!("{}", table);
+            println!("reserved by idle namespaces: {}m cpu, {:.0}Mi mem", cpu, mem);
+            return Ok(());
+        },
+        Some(Command::Stuck { namespace }) => {
+            let pods = kubernetes::get_stuck_pods(source, namespace).await?;
+            if pods.is_empty() {
+                println!("no stuck pods");
+                return Ok(());
+            }
+
+            let mut table = Table::new(utils::parse_stuck_data(&pods));
+            table.with(Style::rounded());
+            println!("{}", table);
+
+            let mut table = Table::new(utils::parse_stuck_namespace_data(&pods));
+            table.with(Style::rounded());
+            println!("{}", table);
+            return Ok(());
+        },
+        Some(Command::Audit { namespace, requests_only }) => {
+            let interval = utils::parse_duration(opts.interval.as_deref().unwrap_or("10s")).map_err(Error::Parse)?;
+            let samples = match opts.utilization {
+                true => opts.samples.unwrap_or(1).max(1),
+                false => 0,
+            };
+            let containers = kubernetes::collect_container_usage(source, namespace, samples, interval, errors).await?;
+            let (rows, workloads) = utils::parse_audit_data(&containers, requests_only);
+
+            let mut table = Table::new(&rows);
+            table.with(Style::rounded());
+            if !opts.utilization {
+                table.with(Disable::column(ByColumnName::new("cpu usage")));
+                table.with(Disable::column(ByColumnName::new("mem usage")));
+            }
+
+            println!("{}", table);
+            println!("{} containers in {} workloads miss {}", rows.len(), workloads, if requests_only { "requests" } else { "requests or limits" });
+            return Ok(());
+        },
+        Some(Command::OomRisk { namespace, threshold }) => {
+            return print_oom_risk(source, namespace, threshold.unwrap_or(90.0), opts.selector, errors).await;
+        },
+        Some(Command::Pressure { since }) => {
+            let since = utils::parse_duration(since.as_deref().unwrap_or("24h")).map_err(Error::Parse)?;
+            return print_pressure(source, since, opts.utilization, opts.selector, errors).await;
+        },
+        Some(Command::Rightsize { namespace, headroom }) => {
+            let interval = utils::parse_duration(opts.interval.as_deref().unwrap_or("10s")).map_err(Error::Parse)?;
+            return print_rightsize(source, namespace, headroom.unwrap_or(20.0), opts.samples.unwrap_or(1), interval, sort_by, errors).await;
+        },
+        None => (),
+    }
+
+    if let Some(m) = opts.matrix {
+        let resource = match utils::Filter::from_str(&m) {
+            Ok(r @ (utils::Filter::Cpu | utils::Filter::Mem)) => r,
+            _ => return Err(Error::Parse(format!("invalid matrix resource {}", m))),
+        };
+
+        let mut matrix = BTreeMap::new();
+        kubernetes::collect_matrix(source, &mut matrix, opts.selector, opts.node_group).await?;
+
+        let mut table = Builder::from(utils::parse_matrix_data(matrix, resource)).build();
+        table.with(Style::rounded());
+
+        println!("{}", table);
+        return Ok(());
+    }
+
+    if opts.tree {
+        let mut tree = BTreeMap::new();
+        kubernetes::collect_tree(source, &mut tree, opts.utilization, opts.selector, errors).await?;
+
+        let mut table = Table::new(utils::parse_tree_data(tree));
+        table.with(Style::rounded());
+        if !opts.utilization {
+            table.with(Disable::column(ByColumnName::new("cpu usage")));
+            table.with(Disable::column(ByColumnName::new("mem usage")));
+        }
+
+        println!("{}", table);
+        return Ok(());
+    }
+
+    if let Some(samples) = opts.samples {
+        let interval = utils::parse_duration(opts.interval.as_deref().unwrap_or("10s")).map_err(Error::Parse)?;
+        let rows = kubernetes::sample_resource_usage(source, resource_type, opts.selector, samples.max(1), interval, errors).await?;
+
+        let mut table = Table::new(utils::parse_usage_samples(&rows));
+        table.with(Style::rounded());
+
+        println!("{}", table);
+        return Ok(());
+    }
+
+    let show_free = opts.free && matches!(resource_type, kubernetes::ResourceType::Node);
+    let resource_req = kubernetes::get_resource_requests(source, resource_type, opts.utilization, opts.selector, errors).await?;
+    // only the kubelet summary api serves storage usage
+    let show_storage_usage = resource_req.iter().any(|r| r.storage_usage.is_some());
 
     let data = utils::parse_resource_data(resource_req, sort_by);
     let mut table = Table::new(&data);
@@ -73,7 +428,260 @@ async fn main() {
     if !opts.utilization {
         table.with(Disable::column(ByColumnName::new("cpu usage")));
         table.with(Disable::column(ByColumnName::new("mem usage")));
+        table.with(Disable::column(ByColumnName::new("cpu efficiency")));
+        table.with(Disable::column(ByColumnName::new("mem efficiency")));
+    }
+    if !show_storage_usage {
+        table.with(Disable::column(ByColumnName::new("storage usage")));
+    }
+    if !show_free {
+        table.with(Disable::column(ByColumnName::new("cpu free")));
+        table.with(Disable::column(ByColumnName::new("mem free")));
+        table.with(Disable::column(ByColumnName::new("storage free")));
+        table.with(Disable::column(ByColumnName::new("pods free")));
+    }
+
+    println!("{}", table);
+    Ok(())
+}
+
+async fn print_node_detail(source: &source::Source, name: &str, cpu: Option<String>, memory: Option<String>, sort_by: utils::Filter, utilization: bool, errors: &mut Vec<Error>) -> Result<(), Error> {
+    let cpu = cpu.map(utils::parse_cpu_requests).transpose().map_err(|e| e.context("Error parsing --cpu"))?;
+    let memory = memory.map(utils::parse_capacity_requests).transpose().map_err(|e| e.context("Error parsing --memory"))?;
+
+    let mut detail = kubernetes::get_node_detail(source, name, utilization, errors).await?;
+
+    let mut table = Table::new(utils::parse_node_detail(&detail));
+    table.with(Style::rounded());
+    if !utilization {
+        table.with(Disable::column(ByColumnName::new("usage")));
+    }
+
+    println!("{}", table);
+
+    if !detail.filesystems.is_empty() {
+        let filesystems: Vec<String> = detail.filesystems.iter()
+            .map(|(name, used, capacity)| format!("{} {:.0}Mi / {:.0}Mi ({:.2}%)", name, used, capacity, (used / capacity) * 100.0))
+            .collect();
+        println!("filesystems: {}", filesystems.join(", "));
+    }
+
+    let conditions: Vec<String> = detail.conditions.iter().map(|(t, s)| format!("{}={}", t, s)).collect();
+    println!("conditions: {}", conditions.join(", "));
+    match detail.taints.is_empty() {
+        true => println!("taints: <none>"),
+        false => println!("taints: {}", detail.taints.join(", ")),
+    }
+
+    println!("top cpu consumers: {}", utils::get_top_consumers(&mut detail.pods, utils::Filter::Cpu, utilization, 3));
+    println!("top mem consumers: {}", utils::get_top_consumers(&mut detail.pods, utils::Filter::Mem, utilization, 3));
+
+    if cpu.is_some() || memory.is_some() {
+        let cpu = cpu.unwrap_or(0);
+        let mem = memory.unwrap_or(0.0);
+        let (cpu_requests, mem_requests, _, pods) = detail.requests;
+        let (cpu_total, mem_total, _, pods_total) = detail.allocatable;
+
+        let count = utils::get_fit_count(
+            cpu_total.saturating_sub(cpu_requests),
+            mem_total - mem_requests,
+            pods_total.saturating_sub(pods),
+            cpu,
+            mem,
+        );
+        println!("headroom: {} more pods of {}m cpu and {:.0}Mi mem fit on {}", count, cpu, mem, detail.name);
+    }
+
+    utils::sort_pod_resources(&mut detail.pods, &sort_by, utilization);
+    let pods: Vec<kubernetes::PodStatus> = detail.pods.iter()
+        .map(|p| kubernetes::PodStatus::new(p.namespace.clone(), p.name.clone(), &p.resources))
+        .collect();
+
+    let mut table = Table::new(pods);
+    table.with(Style::rounded());
+    if !utilization {
+        table.with(Disable::column(ByColumnName::new("cpu usage")));
+        table.with(Disable::column(ByColumnName::new("mem usage")));
+    }
+
+    println!("{}", table);
+
+    if !detail.volumes.is_empty() {
+        let mut table = Table::new(utils::parse_volume_data(&detail.volumes));
+        table.with(Style::rounded());
+        println!("{}", table);
+    }
+    Ok(())
+}
+
+async fn print_fit(source: &source::Source, file: Option<String>, cpu: Option<String>, memory: Option<String>, selector: Option<String>) -> Result<(), Error> {
+    let mut pod = scheduler::PodShape::default();
+
+    let cpu = cpu.map(utils::parse_cpu_requests).transpose().map_err(|e| e.context("Error parsing --cpu"))?;
+    let memory = memory.map(utils::parse_capacity_requests).transpose().map_err(|e| e.context("Error parsing --memory"))?;
+
+    if let Some(file) = file {
+        let manifest = std::fs::read_to_string(&file).map_err(|e| Error::from_io(e, format!("Error reading {}", file)))?;
+
+        let p: Pod = serde_yaml::from_str(&manifest).map_err(|e| Error::Parse(format!("Error parsing pod manifest {}", e)))?;
+
+        if let Some(spec) = p.spec {
+            pod = kubernetes::get_pod_shape(p.metadata.name.unwrap_or_default(), spec).await
+                .map_err(|e| e.context(&format!("Error parsing requests of {}", file)))?;
+        }
+    }
+
+    if let Some(cpu) = cpu {
+        pod.cpu_requests = cpu;
+    }
+    if let Some(memory) = memory {
+        pod.mem_requests = memory;
+    }
+
+    let (nodes, _) = kubernetes::get_cluster_state(source, selector).await?;
+
+    let mut table = Table::new(utils::parse_fit_data(&nodes, &pod));
+    table.with(Style::rounded());
+
+    println!("{}", table);
+    println!("{} replicas of {}m cpu and {:.0}Mi mem fit in the cluster", scheduler::count_replicas(&nodes, &pod), pod.cpu_requests, pod.mem_requests);
+    Ok(())
+}
+
+async fn print_drain_sim(source: &source::Source, drained: Vec<String>, selector: Option<String>) -> Result<(), Error> {
+    let (mut nodes, pods) = kubernetes::get_cluster_state(source, selector).await?;
+
+    for name in &drained {
+        if !nodes.iter().any(|n| &n.name == name) {
+            return Err(Error::NotFound(format!("node {} not found", name)));
+        }
+    }
+
+    let placements = scheduler::simulate_drain(&mut nodes, &pods, &drained);
+    let pending = placements.iter().filter(|(_, to)| to.is_err()).count();
+
+    let mut table = Table::new(utils::parse_placement_data(&placements));
+    table.with(Style::rounded());
+
+    println!("{}", table);
+    println!("{} pods rescheduled, {} pods pending", placements.len() - pending, pending);
+    Ok(())
+}
+
+async fn print_what_if_scale(source: &source::Source, workload: &str, namespace: &str, replicas: usize, selector: Option<String>) -> Result<(), Error> {
+    let (current, spec) = kubernetes::get_workload_template(source, workload, namespace).await?;
+
+    if replicas <= current {
+        println!("{} already has {} replicas, nothing to add", workload, current);
+        return Ok(());
+    }
+
+    let pod = kubernetes::get_pod_shape(format!("{}/{}", namespace, workload), spec).await?;
+    let (nodes, pods) = kubernetes::get_cluster_state(source, selector).await?;
+
+    let mut trial = nodes.clone();
+    let (placed, pending) = scheduler::simulate_scale(&mut trial, &pod, replicas - current);
+
+    let records: Vec<Vec<String>> = std::iter::once(vec![String::from("node"), String::from("replicas")])
+        .chain(placed.iter().map(|(node, count)| vec![node.clone(), format!("{}", count)]))
+        .collect();
+    let mut table = Builder::from(records).build();
+    table.with(Style::rounded());
+
+    println!("{}", table);
+    println!("{} of {} new replicas of {}m cpu and {:.0}Mi mem fit", replicas - current - pending, replicas - current, pod.cpu_requests, pod.mem_requests);
+
+    if pending > 0 {
+        let mut table = Table::new(utils::parse_nodes_needed_data(scheduler::get_nodes_needed(&nodes, &pods, &pod, pending)));
+        table.with(Style::rounded());
+
+        println!("{}", table);
+    }
+
+    Ok(())
+}
+
+async fn print_rightsize(source: &source::Source, namespace: Option<String>, headroom: f32, samples: usize, interval: std::time::Duration, sort_by: utils::Filter, errors: &mut Vec<Error>) -> Result<(), Error> {
+    let containers = kubernetes::collect_container_usage(source, namespace, samples.max(1), interval, errors).await?;
+    let (rows, (cpu, mem)) = utils::parse_rightsize_data(&containers, headroom, sort_by);
+    let (cpu_total, mem_total, _, _) = kubernetes::get_cluster_node_info(source).await?;
+
+    let mut table = Table::new(rows);
+    table.with(Style::rounded());
+
+    println!("{}", table);
+    println!(
+        "reclaimed: {}m cpu ({:.2}% of allocatable), {:.0}Mi mem ({:.2}% of allocatable)",
+        cpu, (cpu as f32 / cpu_total as f32) * 100.0, mem, (mem / mem_total) * 100.0,
+    );
+    if containers.iter().any(|cu| utils::get_recommendation(cu, headroom).is_some_and(|r| r.1.is_none())) {
+        println!("limits need --samples 2 or more, or prometheus with --window");
+    }
+    Ok(())
+}
+
+async fn print_oom_risk(source: &source::Source, namespace: Option<String>, threshold: f32, selector: Option<String>, errors: &mut Vec<Error>) -> Result<(), Error> {
+    let containers = kubernetes::get_container_memory(source, namespace, errors).await?;
+    let nodes = kubernetes::get_resource_requests(source, kubernetes::ResourceType::Node, true, selector, errors).await?;
+
+    let mut table = Table::new(utils::parse_oom_risk_data(&containers, threshold));
+    table.with(Style::rounded());
+    println!("{}", table);
+
+    let mut table = Table::new(utils::parse_node_memory_data(&nodes, threshold));
+    table.with(Style::rounded());
+    println!("{}", table);
+    Ok(())
+}
+
+async fn print_pressure(source: &source::Source, since: std::time::Duration, utilization: bool, selector: Option<String>, errors: &mut Vec<Error>) -> Result<(), Error> {
+    let since = Utc::now() - k8s_openapi::chrono::Duration::from_std(since).map_err(|e| Error::Parse(e.to_string()))?;
+    let nodes = kubernetes::get_node_pressure(source, selector, utilization, since, errors).await?;
+
+    let mut table = Table::new(utils::parse_node_pressure_data(&nodes));
+    table.with(Style::rounded());
+    if !utilization {
+        table.with(Disable::column(ByColumnName::new("mem usage")));
+    }
+    println!("{}", table);
+
+    let events = utils::parse_pressure_event_data(&nodes);
+    if !events.is_empty() {
+        let mut table = Table::new(events);
+        table.with(Style::rounded());
+        println!("{}", table);
+    }
+    Ok(())
+}
+
+async fn print_impact(source: &source::Source, files: Vec<String>, selector: Option<String>) -> Result<(), Error> {
+    let mut workloads = Vec::new();
+
+    let files = match files.is_empty() {
+        true => vec![String::from("-")],
+        false => files,
+    };
+
+    for file in files {
+        let content = match file.as_str() {
+            "-" => std::io::read_to_string(std::io::stdin()),
+            _ => std::fs::read_to_string(&file),
+        };
+
+        let content = content.map_err(|e| Error::from_io(e, format!("Error reading {}", file)))?;
+
+        workloads.append(&mut manifest::parse_manifests(&content)?);
     }
 
+    let (nodes, _) = kubernetes::get_cluster_state(source, selector).await?;
+    let requests = manifest::get_workloads_requests(workloads, &nodes).await?;
+
+    let mut table = Table::new(utils::parse_workload_data(&requests));
+    table.with(Style::rounded());
     println!("{}", table);
-}
\ No newline at end of file
+
+    let mut table = Table::new(utils::parse_impact_data(&nodes, &requests));
+    table.with(Style::rounded());
+    println!("{}", table);
+    Ok(())
+}
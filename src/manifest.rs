@@ -0,0 +1,95 @@
+use k8s_openapi::api::{apps::v1::{DaemonSet, Deployment, ReplicaSet, StatefulSet}, batch::v1::Job, core::v1::{Pod, PodSpec}};
+use serde::Deserialize;
+
+use super::{error::Error, kubernetes, scheduler};
+
+pub enum Replicas {
+    Count(usize),
+    PerNode,
+}
+
+pub struct Workload {
+    pub name: String,
+    pub replicas: Replicas,
+    pub spec: PodSpec,
+}
+
+pub fn parse_manifests(content: &str) -> Result<Vec<Workload>, Error> {
+    let mut workloads = Vec::new();
+
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = match serde_yaml::Value::deserialize(document) {
+            Ok(value) => value,
+            Err(e) => return Err(Error::Parse(format!("Error parsing manifest {}", e))),
+        };
+
+        parse_manifest(value, &mut workloads)?;
+    }
+
+    Ok(workloads)
+}
+
+fn parse_manifest(value: serde_yaml::Value, workloads: &mut Vec<Workload>) -> Result<(), Error> {
+    let kind = value.get("kind").and_then(|k| k.as_str()).unwrap_or_default().to_string();
+    let name = value.get("metadata").and_then(|m| m.get("name")).and_then(|n| n.as_str()).unwrap_or_default();
+    let name = format!("{}/{}", kind.to_lowercase(), name);
+
+    let parse_error = |e: serde_yaml::Error| Error::Parse(format!("Error parsing {} {}", name, e));
+
+    let (replicas, template) = match kind.as_str() {
+        "List" => {
+            if let Some(items) = value.get("items").and_then(|i| i.as_sequence()) {
+                for item in items {
+                    parse_manifest(item.clone(), workloads)?;
+                }
+            }
+            return Ok(());
+        },
+        "Deployment" => {
+            let spec = serde_yaml::from_value::<Deployment>(value).map_err(parse_error)?.spec.unwrap_or_default();
+            (Replicas::Count(spec.replicas.unwrap_or(1).max(0) as usize), spec.template.spec)
+        },
+        "StatefulSet" => {
+            let spec = serde_yaml::from_value::<StatefulSet>(value).map_err(parse_error)?.spec.unwrap_or_default();
+            (Replicas::Count(spec.replicas.unwrap_or(1).max(0) as usize), spec.template.spec)
+        },
+        "ReplicaSet" => {
+            let spec = serde_yaml::from_value::<ReplicaSet>(value).map_err(parse_error)?.spec.unwrap_or_default();
+            (Replicas::Count(spec.replicas.unwrap_or(1).max(0) as usize), spec.template.and_then(|t| t.spec))
+        },
+        "DaemonSet" => {
+            let spec = serde_yaml::from_value::<DaemonSet>(value).map_err(parse_error)?.spec.unwrap_or_default();
+            (Replicas::PerNode, spec.template.spec)
+        },
+        "Job" => {
+            let spec = serde_yaml::from_value::<Job>(value).map_err(parse_error)?.spec.unwrap_or_default();
+            (Replicas::Count(spec.parallelism.unwrap_or(1).max(0) as usize), spec.template.spec)
+        },
+        "Pod" => (Replicas::Count(1), serde_yaml::from_value::<Pod>(value).map_err(parse_error)?.spec),
+        _ => return Ok(()),
+    };
+
+    if let Some(spec) = template {
+        workloads.push(Workload { name, replicas, spec });
+    }
+
+    Ok(())
+}
+
+pub async fn get_workloads_requests(workloads: Vec<Workload>, nodes: &[scheduler::NodeState]) -> Result<Vec<(String, usize, scheduler::PodShape)>, Error> {
+    let mut requests = Vec::new();
+
+    for workload in workloads {
+        let shape = kubernetes::get_pod_shape(workload.name.clone(), workload.spec).await
+            .map_err(|e| e.context(&format!("Error parsing requests of {}", workload.name)))?;
+
+        let pods = match workload.replicas {
+            Replicas::Count(n) => n,
+            Replicas::PerNode => nodes.iter().filter(|n| n.matches(&shape).is_ok()).count(),
+        };
+
+        requests.push((workload.name, pods, shape));
+    }
+
+    Ok(requests)
+}
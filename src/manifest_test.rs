@@ -0,0 +1,107 @@
+use super::manifest::*;
+
+#[test]
+fn test_parse_manifests() {
+    let content = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 3
+  selector:
+    matchLabels:
+      app: web
+  template:
+    metadata:
+      labels:
+        app: web
+    spec:
+      containers:
+      - name: web
+        image: nginx
+        resources:
+          requests:
+            cpu: 250m
+            memory: 256Mi
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+spec:
+  ports:
+  - port: 80
+---
+apiVersion: apps/v1
+kind: DaemonSet
+metadata:
+  name: agent
+spec:
+  selector:
+    matchLabels:
+      app: agent
+  template:
+    metadata:
+      labels:
+        app: agent
+    spec:
+      containers:
+      - name: agent
+        image: agent
+---
+{"apiVersion": "v1", "kind": "List", "items": [
+  {"apiVersion": "v1", "kind": "Pod", "metadata": {"name": "debug"}, "spec": {"containers": [{"name": "debug", "image": "busybox"}]}},
+  {"apiVersion": "batch/v1", "kind": "Job", "metadata": {"name": "migrate"}, "spec": {"parallelism": 2, "template": {"spec": {"containers": [{"name": "migrate", "image": "migrate"}]}}}}
+]}
+"#;
+
+    let workloads = parse_manifests(content).unwrap();
+    let summary: Vec<(String, Option<usize>)> = workloads.iter().map(|w| match w.replicas {
+        Replicas::Count(n) => (w.name.clone(), Some(n)),
+        Replicas::PerNode => (w.name.clone(), None),
+    }).collect();
+
+    assert_eq!(summary, vec![
+        (String::from("deployment/web"), Some(3)),
+        (String::from("daemonset/agent"), None),
+        (String::from("pod/debug"), Some(1)),
+        (String::from("job/migrate"), Some(2)),
+    ]);
+    assert_eq!(workloads[0].spec.containers[0].name, "web");
+
+    assert!(parse_manifests("kind: Deployment\nspec: [").is_err());
+}
+
+#[tokio::test]
+async fn test_get_workloads_requests() {
+    let content = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: 2
+  selector:
+    matchLabels:
+      app: web
+  template:
+    spec:
+      containers:
+      - name: web
+        image: nginx
+        resources:
+          requests:
+            cpu: "0.25"
+            memory: "100e6"
+"#;
+
+    let requests = get_workloads_requests(parse_manifests(content).unwrap(), &[]).await.unwrap();
+    let (name, pods, shape) = &requests[0];
+
+    assert_eq!((name.as_str(), *pods, shape.cpu_requests), ("deployment/web", 2, 250));
+    assert_eq!(shape.mem_requests, 95.36743);
+
+    let workloads = parse_manifests(&content.replace("\"0.25\"", "2x")).unwrap();
+    assert!(get_workloads_requests(workloads, &[]).await.is_err());
+}
@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+
+use kube::api::ListParams;
+
+use super::{error::Error, kubernetes, source::DataSource, utils::{parse_cpu_requests, parse_capacity_requests}};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Resources {
+    pub cpu: u32,
+    pub memory: f32,
+    pub storage: f32,
+    pub pods: usize,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Usage {
+    pub cpu: u32,
+    pub memory: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NodeSnapshot {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub allocatable: Resources,
+    pub requests: Resources,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PodSnapshot {
+    pub namespace: String,
+    pub name: String,
+    pub node: Option<String>,
+    pub running: bool,
+    pub requests: Resources,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Totals {
+    pub allocatable: Resources,
+    pub requests: Resources,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ClusterSnapshot {
+    pub nodes: Vec<NodeSnapshot>,
+    pub pods: Vec<PodSnapshot>,
+    pub totals: Totals,
+    /// data that couldn't be read, the affected usage is left empty
+    pub errors: Vec<Error>,
+}
+
+impl Resources {
+    fn add(&mut self, other: &Resources) {
+        self.cpu += other.cpu;
+        self.memory += other.memory;
+        self.storage += other.storage;
+        self.pods += other.pods;
+    }
+}
+
+impl Usage {
+    fn add(&mut self, other: &Usage) {
+        self.cpu += other.cpu;
+        self.memory += other.memory;
+    }
+}
+
+impl ClusterSnapshot {
+    /// requests and usage of the running pods, per namespace
+    pub fn namespaces(&self) -> BTreeMap<String, (Resources, Option<Usage>)> {
+        let mut namespaces: BTreeMap<String, (Resources, Option<Usage>)> = BTreeMap::new();
+
+        for pod in self.pods.iter().filter(|p| p.running) {
+            let entry = namespaces.entry(pod.namespace.clone()).or_default();
+            entry.0.add(&pod.requests);
+            if let Some(usage) = &pod.usage {
+                entry.1.get_or_insert_with(Usage::default).add(usage);
+            }
+        }
+
+        namespaces
+    }
+}
+
+/// collects nodes matching the label selector and all pods, with usage from the metrics api when utilization is set
+pub async fn get_cluster_snapshot(source: &impl DataSource, selector: Option<String>, utilization: bool) -> Result<ClusterSnapshot, Error> {
+    let mut lp = ListParams::default();
+    if let Some(labels) = selector {
+        lp = ListParams::default().labels(&labels);
+    }
+
+    let nodes = source.list_nodes(&lp).await.map_err(|e| e.context("Error listing nodes"))?;
+    let pods = source.list_pods(&ListParams::default()).await.map_err(|e| e.context("Error listing pods"))?;
+
+    let mut snapshot = ClusterSnapshot::default();
+    let mut pod_usage = BTreeMap::new();
+    let utilization = match utilization {
+        true => match source.metrics_available() {
+            Ok(()) => true,
+            Err(e) => {
+                snapshot.errors.push(e);
+                false
+            }
+        },
+        false => false,
+    };
+
+    if utilization {
+        let metrics = match source.list_pod_metrics(None).await {
+            Ok(metrics) => metrics,
+            Err(e) => {
+                snapshot.errors.push(e.context("Error getting pod utilization information"));
+                Vec::new()
+            }
+        };
+
+        for pm in metrics {
+            let mut usage = Usage::default();
+            for container in &pm.containers {
+                usage.cpu += parse_cpu_requests(container.usage.cpu.0.to_string())?;
+                usage.memory += parse_capacity_requests(container.usage.memory.0.to_string())?;
+            }
+            pod_usage.insert((pm.metadata.namespace.unwrap_or_default(), pm.metadata.name.unwrap_or_default()), usage);
+        }
+    }
+
+    let mut node_index = BTreeMap::new();
+
+    for node in nodes {
+        let name = node.metadata.name.clone().unwrap_or_default();
+        let (cpu, memory, storage, pods) = kubernetes::get_node_allocatable(&node)?;
+
+        let mut usage = None;
+        if utilization {
+            match kubernetes::get_node_utilization(source, &name).await {
+                Ok((cpu, memory, _)) => usage = Some(Usage { cpu, memory }),
+                Err(e) => snapshot.errors.push(e),
+            }
+        }
+
+        node_index.insert(name.clone(), snapshot.nodes.len());
+        snapshot.nodes.push(NodeSnapshot {
+            name,
+            labels: node.metadata.labels.clone().unwrap_or_default(),
+            allocatable: Resources { cpu, memory, storage, pods },
+            requests: Resources::default(),
+            usage,
+        });
+    }
+
+    for pod in pods {
+        let running = kubernetes::is_pod_running(&pod);
+        let namespace = pod.metadata.namespace.clone().unwrap_or_default();
+        let name = pod.metadata.name.clone().unwrap_or_default();
+        let node = pod.spec.as_ref().and_then(|s| s.node_name.clone());
+
+        let (cpu, memory, storage) = match pod.spec {
+            Some(spec) => kubernetes::get_pod_resources_req(spec).await?,
+            None => (0, 0.0, 0.0),
+        };
+        let requests = Resources { cpu, memory, storage, pods: 1 };
+
+        if running {
+            if let Some(i) = node.as_ref().and_then(|n| node_index.get(n)) {
+                snapshot.nodes[*i].requests.add(&requests);
+            }
+        }
+
+        let usage = pod_usage.get(&(namespace.clone(), name.clone())).copied();
+        snapshot.pods.push(PodSnapshot { namespace, name, node, running, requests, usage });
+    }
+
+    for node in &snapshot.nodes {
+        snapshot.totals.allocatable.add(&node.allocatable);
+        snapshot.totals.requests.add(&node.requests);
+        if let Some(usage) = &node.usage {
+            snapshot.totals.usage.get_or_insert_with(Usage::default).add(usage);
+        }
+    }
+
+    Ok(snapshot)
+}
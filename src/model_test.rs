@@ -0,0 +1,70 @@
+use super::{error::Error, model::*, snapshot::Snapshot};
+
+#[tokio::test]
+async fn test_get_cluster_snapshot() {
+    let content = r#"
+kind: NodeList
+items:
+- metadata:
+    name: node-1
+    labels: {role: worker}
+  status:
+    allocatable: {cpu: "4", memory: 8Gi, ephemeral-storage: 10Gi, pods: "110"}
+- metadata:
+    name: node-2
+    labels: {role: infra}
+  status:
+    allocatable: {cpu: "2", memory: 4Gi, ephemeral-storage: 10Gi, pods: "110"}
+---
+kind: PodList
+items:
+- metadata: {name: web, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: web
+      resources:
+        requests: {cpu: 500m, memory: 512Mi}
+  status: {phase: Running}
+- metadata: {name: done, namespace: default}
+  spec:
+    nodeName: node-1
+    containers:
+    - name: done
+      resources:
+        requests: {cpu: "1", memory: 1Gi}
+  status: {phase: Succeeded}
+---
+kind: NodeMetricsList
+items:
+- metadata: {name: node-1}
+  usage: {cpu: 1500m, memory: 2Gi}
+---
+kind: PodMetricsList
+items:
+- metadata: {name: web, namespace: default}
+  containers:
+  - name: web
+    usage: {cpu: 250m, memory: 300Mi}
+"#;
+
+    let mut source = Snapshot::default();
+    source.parse(content).unwrap();
+
+    let snapshot = get_cluster_snapshot(&source, Some(String::from("role=worker")), true).await.unwrap();
+
+    assert_eq!(snapshot.nodes.len(), 1);
+    assert_eq!(snapshot.nodes[0].requests, Resources { cpu: 500, memory: 512.0, storage: 0.0, pods: 1 });
+    assert_eq!(snapshot.nodes[0].usage, Some(Usage { cpu: 1500, memory: 2048.0 }));
+    assert_eq!(snapshot.totals.allocatable.cpu, 4000);
+    assert_eq!(snapshot.pods.len(), 2);
+
+    let namespaces = snapshot.namespaces();
+    assert_eq!(namespaces["default"], (Resources { cpu: 500, memory: 512.0, storage: 0.0, pods: 1 }, Some(Usage { cpu: 250, memory: 300.0 })));
+
+    assert!(snapshot.errors.is_empty());
+
+    let snapshot = get_cluster_snapshot(&source, None, true).await.unwrap();
+    assert_eq!(snapshot.nodes[1].usage, None);
+    assert!(matches!(snapshot.errors[..], [Error::MetricsUnavailable(_)]));
+}
@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+
+use hyper::{body, client::HttpConnector, Client, Uri};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::core::ObjectMeta;
+use serde::Deserialize;
+
+use super::{error::Error, kubernetes::{ContainerMetrics, NodeMetrics, PodMetrics, Usage}};
+
+/// labels and value of a single series in a query result
+pub type Sample = (BTreeMap<String, String>, f64);
+
+const CONTAINER_FILTER: &str = r#"container!="",container!="POD""#;
+
+/// usage read from a prometheus compatible api, as a quantile over a window or as the latest value
+#[derive(Clone)]
+pub struct Prometheus {
+    url: String,
+    window: Option<String>,
+    quantile: f64,
+    client: Client<HttpsConnector<HttpConnector>>,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    data: Option<QueryData>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QueryData {
+    result_type: String,
+    result: Vec<VectorSample>,
+}
+
+#[derive(Deserialize)]
+struct VectorSample {
+    metric: BTreeMap<String, String>,
+    value: (f64, String),
+}
+
+impl Prometheus {
+    pub fn new(url: &str, window: Option<String>, quantile: f64) -> Result<Prometheus, Error> {
+        if let Some(window) = &window {
+            if !is_duration(window) {
+                return Err(Error::Parse(format!("invalid window {}, expected a duration like 7d or 12h", window)));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&quantile) {
+            return Err(Error::Parse(format!("invalid quantile {}, expected a value between 0 and 1", quantile)));
+        }
+
+        url.parse::<Uri>().map_err(|e| Error::Parse(format!("invalid prometheus url {} {}", url, e)))?;
+
+        let connector = HttpsConnectorBuilder::new()
+            .with_native_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+
+        Ok(Prometheus {
+            url: url.trim_end_matches('/').to_string(),
+            window,
+            quantile,
+            client: Client::builder().build(connector),
+        })
+    }
+
+    /// the promql for cpu cores and memory bytes per group, over the window when one is set
+    pub fn usage_queries(&self, group: &str, filter: &str) -> (String, String) {
+        let (cpu, mem) = usage_queries(group, filter);
+
+        match &self.window {
+            Some(window) => (
+                format!("quantile_over_time({}, ({})[{}:5m])", self.quantile, cpu, window),
+                format!("quantile_over_time({}, ({})[{}:5m])", self.quantile, mem, window),
+            ),
+            None => (cpu, mem),
+        }
+    }
+
+    /// the promql for the highest cpu cores and memory bytes per group over the window, none without a window
+    pub fn peak_queries(&self, group: &str, filter: &str) -> Option<(String, String)> {
+        let (cpu, mem) = usage_queries(group, filter);
+
+        self.window.as_ref().map(|window| (
+            format!("max_over_time(({})[{}:5m])", cpu, window),
+            format!("max_over_time(({})[{}:5m])", mem, window),
+        ))
+    }
+
+    async fn query(&self, promql: &str) -> Result<Vec<Sample>, Error> {
+        let query: String = form_urlencoded::Serializer::new(String::new()).append_pair("query", promql).finish();
+        let uri = format!("{}/api/v1/query?{}", self.url, query).parse::<Uri>()
+            .map_err(|e| Error::Parse(format!("invalid prometheus url {} {}", self.url, e)))?;
+
+        let unavailable = |e: String| Error::MetricsUnavailable(format!("Error querying prometheus at {} {}", self.url, e));
+
+        let response = self.client.get(uri).await.map_err(|e| unavailable(e.to_string()))?;
+        let status = response.status();
+        let content = body::to_bytes(response.into_body()).await.map_err(|e| unavailable(e.to_string()))?;
+
+        parse_response(&content).map_err(|e| match status.is_success() {
+            true => e,
+            false => unavailable(format!("{} {}", status, e)),
+        })
+    }
+
+    pub async fn get_node_metrics(&self, name: &str) -> Result<NodeMetrics, Error> {
+        let (cpu, mem) = self.usage_queries("node", &format!(r#",node="{}""#, name));
+
+        let cpu = self.query(&cpu).await?;
+        let mem = self.query(&mem).await?;
+
+        match (cpu.first(), mem.first()) {
+            (Some((_, cpu)), Some((_, mem))) => Ok(NodeMetrics {
+                metadata: ObjectMeta { name: Some(name.to_string()), ..Default::default() },
+                usage: usage(*cpu, *mem),
+                filesystems: Vec::new(),
+                volumes: Vec::new(),
+            }),
+            _ => Err(Error::MetricsUnavailable(format!("prometheus has no usage for node {}", name))),
+        }
+    }
+
+    pub async fn list_pod_metrics(&self, namespace: Option<&str>) -> Result<Vec<PodMetrics>, Error> {
+        let filter = namespace.map(|n| format!(r#",namespace="{}""#, n)).unwrap_or_default();
+        let (cpu, mem) = self.usage_queries("namespace, pod, container", &filter);
+
+        let cpu = self.query(&cpu).await?;
+        let mem = self.query(&mem).await?;
+
+        Ok(join_pod_metrics(&cpu, &mem))
+    }
+
+    /// highest usage of each container over the window, none without a window
+    pub async fn list_pod_peak_metrics(&self, namespace: Option<&str>) -> Result<Option<Vec<PodMetrics>>, Error> {
+        let filter = namespace.map(|n| format!(r#",namespace="{}""#, n)).unwrap_or_default();
+        let (cpu, mem) = match self.peak_queries("namespace, pod, container", &filter) {
+            Some(queries) => queries,
+            None => return Ok(None),
+        };
+
+        let cpu = self.query(&cpu).await?;
+        let mem = self.query(&mem).await?;
+
+        Ok(Some(join_pod_metrics(&cpu, &mem)))
+    }
+}
+
+fn usage_queries(group: &str, filter: &str) -> (String, String) {
+    (
+        format!("sum by ({}) (rate(container_cpu_usage_seconds_total{{{}{}}}[5m]))", group, CONTAINER_FILTER, filter),
+        format!("sum by ({}) (container_memory_working_set_bytes{{{}{}}})", group, CONTAINER_FILTER, filter),
+    )
+}
+
+fn usage(cpu: f64, mem: f64) -> Usage {
+    Usage {
+        cpu: Quantity(format!("{}n", (cpu * 1e9) as u64)),
+        memory: Quantity(format!("{}", mem as u64)),
+        storage: None,
+    }
+}
+
+fn is_duration(window: &str) -> bool {
+    match window.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((i, _)) => i > 0 && matches!(&window[i..], "s" | "m" | "h" | "d" | "w" | "y"),
+        None => false,
+    }
+}
+
+pub fn parse_response(content: &[u8]) -> Result<Vec<Sample>, Error> {
+    let response: QueryResponse = serde_json::from_slice(content)
+        .map_err(|e| Error::Parse(format!("Error parsing prometheus response {}", e)))?;
+
+    if response.status != "success" {
+        return Err(Error::MetricsUnavailable(format!("prometheus query failed {}", response.error.unwrap_or_default())));
+    }
+
+    let data = match response.data {
+        Some(data) if data.result_type == "vector" => data,
+        _ => return Err(Error::Parse(String::from("Error parsing prometheus response, expected a vector"))),
+    };
+
+    data.result.into_iter()
+        .map(|s| match s.value.1.parse::<f64>() {
+            Ok(v) if v.is_finite() => Ok((s.metric, v)),
+            _ => Err(Error::Parse(format!("Error parsing prometheus value {}", s.value.1))),
+        })
+        .collect()
+}
+
+/// pod metrics from per container cpu and memory samples labelled with namespace, pod and container
+pub fn join_pod_metrics(cpu: &[Sample], mem: &[Sample]) -> Vec<PodMetrics> {
+    let mut pods: BTreeMap<(String, String), BTreeMap<String, (f64, f64)>> = BTreeMap::new();
+    let label = |m: &BTreeMap<String, String>, name: &str| m.get(name).cloned().unwrap_or_default();
+
+    for (metric, value) in cpu {
+        let pod = pods.entry((label(metric, "namespace"), label(metric, "pod"))).or_default();
+        pod.entry(label(metric, "container")).or_default().0 = *value;
+    }
+    for (metric, value) in mem {
+        let pod = pods.entry((label(metric, "namespace"), label(metric, "pod"))).or_default();
+        pod.entry(label(metric, "container")).or_default().1 = *value;
+    }
+
+    pods.into_iter().map(|((namespace, name), containers)| PodMetrics {
+        metadata: ObjectMeta { name: Some(name), namespace: Some(namespace), ..Default::default() },
+        containers: containers.into_iter()
+            .map(|(name, (cpu, mem))| ContainerMetrics { name, usage: usage(cpu, mem) })
+            .collect(),
+        storage: None,
+    }).collect()
+}
@@ -0,0 +1,71 @@
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+use super::{error::Error, prometheus::*};
+
+/// serves canned query results, memory for working set queries and cpu for the rest
+async fn stub(cpu: &'static str, mem: &'static str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = vec![0; 8192];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]);
+
+            let result = match request.contains("container_memory_working_set_bytes") {
+                true => mem,
+                false => cpu,
+            };
+            let body = format!(r#"{{"status":"success","data":{{"resultType":"vector","result":[{}]}}}}"#, result);
+            let response = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body);
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    url
+}
+
+#[test]
+fn test_usage_queries() {
+    let prometheus = Prometheus::new("http://localhost:9090", Some(String::from("7d")), 0.95).unwrap();
+    let (cpu, mem) = prometheus.usage_queries("node", r#",node="node-1""#);
+
+    assert_eq!(cpu, r#"quantile_over_time(0.95, (sum by (node) (rate(container_cpu_usage_seconds_total{container!="",container!="POD",node="node-1"}[5m])))[7d:5m])"#);
+    assert_eq!(mem, r#"quantile_over_time(0.95, (sum by (node) (container_memory_working_set_bytes{container!="",container!="POD",node="node-1"}))[7d:5m])"#);
+
+    let (cpu, _) = prometheus.peak_queries("node", r#",node="node-1""#).unwrap();
+    assert_eq!(cpu, r#"max_over_time((sum by (node) (rate(container_cpu_usage_seconds_total{container!="",container!="POD",node="node-1"}[5m])))[7d:5m])"#);
+    assert!(Prometheus::new("http://localhost:9090", None, 0.95).unwrap().peak_queries("node", "").is_none());
+
+    assert!(matches!(Prometheus::new("http://localhost:9090", Some(String::from("7 days")), 0.95), Err(Error::Parse(_))));
+    assert!(matches!(Prometheus::new("http://localhost:9090", None, 95.0), Err(Error::Parse(_))));
+}
+
+#[tokio::test]
+async fn test_prometheus_metrics() {
+    let url = stub(
+        r#"{"metric":{"node":"node-1","namespace":"default","pod":"web","container":"web"},"value":[1700000000,"1.5"]}"#,
+        r#"{"metric":{"node":"node-1","namespace":"default","pod":"web","container":"web"},"value":[1700000000,"2147483648"]}"#,
+    ).await;
+    let prometheus = Prometheus::new(&url, None, 0.95).unwrap();
+
+    let node = prometheus.get_node_metrics("node-1").await.unwrap();
+    assert_eq!(node.usage.cpu.0, "1500000000n");
+    assert_eq!(node.usage.memory.0, "2147483648");
+
+    let pods = prometheus.list_pod_metrics(None).await.unwrap();
+    assert_eq!(pods.len(), 1);
+    assert_eq!(pods[0].metadata.name.as_deref(), Some("web"));
+    assert_eq!(pods[0].containers[0].usage.cpu.0, "1500000000n");
+
+    let url = stub("", "").await;
+    let prometheus = Prometheus::new(&url, None, 0.95).unwrap();
+    assert!(matches!(prometheus.get_node_metrics("node-1").await, Err(Error::MetricsUnavailable(_))));
+}
+
+#[test]
+fn test_parse_response_error() {
+    let result = parse_response(br#"{"status":"error","errorType":"bad_data","error":"parse error"}"#);
+    assert!(matches!(result, Err(Error::MetricsUnavailable(m)) if m.contains("parse error")));
+}
@@ -0,0 +1,387 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{NodeSelector, NodeSelectorRequirement, Taint, Toleration};
+
+use super::utils;
+
+#[derive(Clone, Default)]
+pub struct PodShape {
+    pub name: String,
+    pub cpu_requests: u32,
+    pub mem_requests: f32,
+    pub storage_requests: f32,
+    pub tolerations: Vec<Toleration>,
+    pub node_selector: BTreeMap<String, String>,
+    pub node_affinity: Option<NodeSelector>,
+}
+
+#[derive(Clone, Default)]
+pub struct NodeState {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub taints: Vec<Taint>,
+    pub unschedulable: bool,
+    pub allocatable: (u32, f32, f32, usize),
+    pub requests: (u32, f32, f32, usize),
+}
+
+#[derive(Clone, Default)]
+pub struct ScheduledPod {
+    pub node: String,
+    pub daemonset: bool,
+    pub mirror: bool,
+    pub shape: PodShape,
+}
+
+pub type NodesNeeded = Vec<(String, Result<(usize, usize), String>)>;
+
+#[derive(Clone, Default)]
+pub struct Fragmentation {
+    pub nodes: usize,
+    pub cpu_free: u32,
+    pub cpu_largest: u32,
+    pub mem_free: f32,
+    pub mem_largest: f32,
+    pub index: f32,
+}
+
+impl NodeState {
+    pub fn free(&self) -> (u32, f32, f32, usize) {
+        let (cpu_total, mem_total, storage_total, pods_total) = self.allocatable;
+        let (cpu_requests, mem_requests, storage_requests, pods) = self.requests;
+
+        (
+            cpu_total.saturating_sub(cpu_requests),
+            (mem_total - mem_requests).max(0.0),
+            (storage_total - storage_requests).max(0.0),
+            pods_total.saturating_sub(pods),
+        )
+    }
+
+    pub fn check(&self, pod: &PodShape) -> Result<usize, String> {
+        if self.unschedulable {
+            return Err(String::from("node is unschedulable"));
+        }
+
+        self.matches(pod)?;
+
+        let (cpu_free, mem_free, storage_free, pods_free) = self.free();
+
+        if pods_free == 0 {
+            return Err(String::from("too many pods"));
+        }
+        if cpu_free < pod.cpu_requests {
+            return Err(String::from("insufficient cpu"));
+        }
+        if mem_free < pod.mem_requests {
+            return Err(String::from("insufficient memory"));
+        }
+        if storage_free < pod.storage_requests {
+            return Err(String::from("insufficient ephemeral-storage"));
+        }
+
+        let mut count = utils::get_fit_count(cpu_free, mem_free, pods_free, pod.cpu_requests, pod.mem_requests);
+        if pod.storage_requests > 0.0 {
+            count = count.min((storage_free / pod.storage_requests) as usize);
+        }
+
+        Ok(count)
+    }
+
+    pub fn matches(&self, pod: &PodShape) -> Result<(), String> {
+        if let Some(taint) = get_untolerated_taint(&pod.tolerations, &self.taints) {
+            return Err(format!("untolerated taint {}", format_taint(taint)));
+        }
+
+        if !matches_node_selector(&self.labels, &pod.node_selector) {
+            return Err(String::from("node selector mismatch"));
+        }
+
+        if let Some(affinity) = &pod.node_affinity {
+            if !matches_node_affinity(&self.name, &self.labels, affinity) {
+                return Err(String::from("node affinity mismatch"));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn place(&mut self, pod: &PodShape) {
+        self.requests.0 += pod.cpu_requests;
+        self.requests.1 += pod.mem_requests;
+        self.requests.2 += pod.storage_requests;
+        self.requests.3 += 1;
+    }
+
+    fn free_ratio(&self) -> f32 {
+        let (cpu_total, mem_total, _, _) = self.allocatable;
+        let (cpu_free, mem_free, _, _) = self.free();
+
+        let cpu_ratio = match cpu_total {
+            0 => 0.0,
+            _ => cpu_free as f32 / cpu_total as f32,
+        };
+        let mem_ratio = match mem_total > 0.0 {
+            true => mem_free / mem_total,
+            false => 0.0,
+        };
+
+        cpu_ratio + mem_ratio
+    }
+}
+
+pub fn count_replicas(nodes: &[NodeState], pod: &PodShape) -> usize {
+    nodes.iter().filter_map(|n| n.check(pod).ok()).sum()
+}
+
+pub fn schedule(nodes: &mut [NodeState], pod: &PodShape) -> Result<String, String> {
+    let mut best: Option<(usize, f32)> = None;
+    let mut reasons: BTreeMap<String, usize> = BTreeMap::new();
+
+    for (i, node) in nodes.iter().enumerate() {
+        match node.check(pod) {
+            Ok(_) => {
+                let score = node.free_ratio();
+                match best {
+                    Some((_, s)) if s >= score => (),
+                    _ => best = Some((i, score)),
+                }
+            },
+            Err(reason) => *reasons.entry(reason).or_insert(0) += 1,
+        }
+    }
+
+    match best {
+        Some((i, _)) => {
+            nodes[i].place(pod);
+            Ok(nodes[i].name.clone())
+        },
+        None => {
+            let reasons: Vec<String> = reasons.iter().map(|(r, n)| format!("{} {}", n, r)).collect();
+            Err(format!("0/{} nodes are available: {}", nodes.len(), reasons.join(", ")))
+        },
+    }
+}
+
+pub fn simulate_drain<'a>(nodes: &mut Vec<NodeState>, pods: &'a [ScheduledPod], drained: &[String]) -> Vec<(&'a ScheduledPod, Result<String, String>)> {
+    nodes.retain(|n| !drained.contains(&n.name));
+
+    let mut evicted: Vec<&ScheduledPod> = pods.iter()
+        .filter(|p| drained.contains(&p.node) && !p.daemonset && !p.mirror)
+        .collect();
+    evicted.sort_by(|a, b| {
+        b.shape.cpu_requests.cmp(&a.shape.cpu_requests)
+            .then(b.shape.mem_requests.partial_cmp(&a.shape.mem_requests).unwrap())
+    });
+
+    evicted.into_iter().map(|p| (p, schedule(nodes, &p.shape))).collect()
+}
+
+/// control plane, cordoned and static pod nodes stay, draining them doesn't remove them
+fn is_removable(node: &NodeState, pods: &[ScheduledPod]) -> bool {
+    let control_plane = ["node-role.kubernetes.io/control-plane", "node-role.kubernetes.io/master"].iter()
+        .any(|role| node.labels.contains_key(*role) || node.taints.iter().any(|t| t.key == *role));
+    let mirror = pods.iter().any(|p| p.node == node.name && p.mirror);
+
+    !node.unschedulable && !control_plane && !mirror
+}
+
+pub fn consolidate(nodes: &mut Vec<NodeState>, pods: &mut Vec<ScheduledPod>) -> Vec<String> {
+    let mut removed = Vec::new();
+
+    let mut candidates: Vec<(String, f32)> = nodes.iter()
+        .filter(|n| is_removable(n, pods))
+        .map(|n| (n.name.clone(), n.free_ratio()))
+        .collect();
+    candidates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    for (candidate, _) in candidates {
+        let drained = vec![candidate.clone()];
+        let mut trial = nodes.clone();
+
+        let placements: Vec<(String, String)> = match simulate_drain(&mut trial, pods, &drained)
+            .into_iter()
+            .map(|(p, to)| to.map(|to| (p.shape.name.clone(), to)))
+            .collect::<Result<Vec<(String, String)>, String>>() {
+            Ok(placements) => placements,
+            Err(_) => continue,
+        };
+
+        *nodes = trial;
+        pods.retain(|p| p.node != candidate || !(p.daemonset || p.mirror));
+        for (name, to) in placements {
+            if let Some(p) = pods.iter_mut().find(|p| p.shape.name == name) {
+                p.node = to;
+            }
+        }
+
+        removed.push(candidate);
+    }
+
+    removed
+}
+
+pub fn get_requests_percentage(nodes: &[NodeState]) -> (f32, f32) {
+    let (mut cpu_requests, mut cpu_total, mut mem_requests, mut mem_total) = (0, 0, 0.0, 0.0);
+
+    for node in nodes {
+        cpu_requests += node.requests.0;
+        mem_requests += node.requests.1;
+        cpu_total += node.allocatable.0;
+        mem_total += node.allocatable.1;
+    }
+
+    let percentage = |requests: f32, total: f32| match total > 0.0 {
+        true => (requests / total) * 100.0,
+        false => 0.0,
+    };
+
+    (percentage(cpu_requests as f32, cpu_total as f32), percentage(mem_requests, mem_total))
+}
+
+pub fn simulate_scale(nodes: &mut [NodeState], pod: &PodShape, replicas: usize) -> (BTreeMap<String, usize>, usize) {
+    let mut placed: BTreeMap<String, usize> = BTreeMap::new();
+    let mut pending = 0;
+
+    for _ in 0..replicas {
+        match schedule(nodes, pod) {
+            Ok(node) => *placed.entry(node).or_insert(0) += 1,
+            Err(_) => pending += 1,
+        }
+    }
+
+    (placed, pending)
+}
+
+pub fn get_instance_type(node: &NodeState) -> String {
+    node.labels.get("node.kubernetes.io/instance-type")
+        .or_else(|| node.labels.get("beta.kubernetes.io/instance-type"))
+        .cloned()
+        .unwrap_or_else(|| String::from("<none>"))
+}
+
+pub fn get_nodes_needed(nodes: &[NodeState], pods: &[ScheduledPod], pod: &PodShape, replicas: usize) -> NodesNeeded {
+    let mut instance_types: BTreeMap<String, &NodeState> = BTreeMap::new();
+    for node in nodes {
+        instance_types.entry(get_instance_type(node)).or_insert(node);
+    }
+
+    instance_types.into_iter().map(|(instance_type, node)| {
+        let mut new_node = NodeState {
+            requests: (0, 0.0, 0.0, 0),
+            unschedulable: false,
+            ..node.clone()
+        };
+        for daemonset_pod in pods.iter().filter(|p| p.node == node.name && p.daemonset) {
+            new_node.place(&daemonset_pod.shape);
+        }
+
+        let needed = match new_node.check(pod) {
+            Ok(0) => Err(String::from("does not fit")),
+            Ok(per_node) => Ok((per_node, replicas.div_ceil(per_node))),
+            Err(reason) => Err(reason),
+        };
+
+        (instance_type, needed)
+    }).collect()
+}
+
+/// the largest shape is the free cpu and memory of a single node, the one with the most free cpu,
+/// or the most free memory when `by_memory` is set
+pub fn get_fragmentation(nodes: &[&NodeState], by_memory: bool) -> Fragmentation {
+    let mut f = Fragmentation { nodes: nodes.len(), ..Default::default() };
+
+    for node in nodes.iter().filter(|n| !n.unschedulable) {
+        let (cpu_free, mem_free, _, pods_free) = node.free();
+        if pods_free == 0 {
+            continue;
+        }
+
+        f.cpu_free += cpu_free;
+        f.mem_free += mem_free;
+
+        let larger = match by_memory {
+            true => (mem_free, cpu_free) > (f.mem_largest, f.cpu_largest),
+            false => (cpu_free, mem_free) > (f.cpu_largest, f.mem_largest),
+        };
+        if larger {
+            f.cpu_largest = cpu_free;
+            f.mem_largest = mem_free;
+        }
+    }
+
+    let cpu_index = match f.cpu_free {
+        0 => 0.0,
+        _ => 1.0 - f.cpu_largest as f32 / f.cpu_free as f32,
+    };
+    let mem_index = match f.mem_free > 0.0 {
+        true => 1.0 - f.mem_largest / f.mem_free,
+        false => 0.0,
+    };
+    f.index = (cpu_index + mem_index) / 2.0;
+
+    f
+}
+
+pub fn format_taint(taint: &Taint) -> String {
+    match &taint.value {
+        Some(value) => format!("{}={}:{}", taint.key, value, taint.effect),
+        None => format!("{}:{}", taint.key, taint.effect),
+    }
+}
+
+pub fn get_untolerated_taint<'a>(tolerations: &[Toleration], taints: &'a [Taint]) -> Option<&'a Taint> {
+    taints.iter()
+        .filter(|t| t.effect == "NoSchedule" || t.effect == "NoExecute")
+        .find(|t| !tolerations.iter().any(|toleration| tolerates(toleration, t)))
+}
+
+fn tolerates(toleration: &Toleration, taint: &Taint) -> bool {
+    if let Some(effect) = &toleration.effect {
+        if !effect.is_empty() && effect != &taint.effect {
+            return false;
+        }
+    }
+
+    let key = toleration.key.clone().unwrap_or_default();
+
+    match toleration.operator.as_deref() {
+        Some("Exists") => key.is_empty() || key == taint.key,
+        _ => key == taint.key && toleration.value.clone().unwrap_or_default() == taint.value.clone().unwrap_or_default(),
+    }
+}
+
+pub fn matches_node_selector(labels: &BTreeMap<String, String>, node_selector: &BTreeMap<String, String>) -> bool {
+    node_selector.iter().all(|(k, v)| labels.get(k) == Some(v))
+}
+
+pub fn matches_node_affinity(node_name: &str, labels: &BTreeMap<String, String>, affinity: &NodeSelector) -> bool {
+    let fields = BTreeMap::from([(String::from("metadata.name"), node_name.to_string())]);
+
+    affinity.node_selector_terms.iter().any(|term| {
+        let expressions = term.match_expressions.clone().unwrap_or_default();
+        let match_fields = term.match_fields.clone().unwrap_or_default();
+
+        if expressions.is_empty() && match_fields.is_empty() {
+            return false;
+        }
+
+        expressions.iter().all(|r| matches_requirement(labels.get(&r.key), r))
+            && match_fields.iter().all(|r| matches_requirement(fields.get(&r.key), r))
+    })
+}
+
+fn matches_requirement(value: Option<&String>, requirement: &NodeSelectorRequirement) -> bool {
+    let values = requirement.values.clone().unwrap_or_default();
+
+    match (requirement.operator.as_str(), value) {
+        ("In", Some(v)) => values.contains(v),
+        ("NotIn", Some(v)) => !values.contains(v),
+        ("NotIn", None) => true,
+        ("Exists", Some(_)) => true,
+        ("DoesNotExist", None) => true,
+        ("Gt", Some(v)) => matches!((v.parse::<i64>(), values.first().map(|n| n.parse::<i64>())), (Ok(v), Some(Ok(n))) if v > n),
+        ("Lt", Some(v)) => matches!((v.parse::<i64>(), values.first().map(|n| n.parse::<i64>())), (Ok(v), Some(Ok(n))) if v < n),
+        _ => false,
+    }
+}
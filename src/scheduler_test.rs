@@ -0,0 +1,280 @@
+use std::collections::BTreeMap;
+
+use k8s_openapi::api::core::v1::{NodeSelector, NodeSelectorRequirement, NodeSelectorTerm, Taint, Toleration};
+
+use super::scheduler::*;
+
+fn node(name: &str, allocatable: (u32, f32, f32, usize), requests: (u32, f32, f32, usize)) -> NodeState {
+    NodeState {
+        name: String::from(name),
+        labels: BTreeMap::from([(String::from("pool"), String::from("general"))]),
+        allocatable,
+        requests,
+        ..Default::default()
+    }
+}
+
+fn pod(cpu_requests: u32, mem_requests: f32) -> PodShape {
+    PodShape {
+        cpu_requests,
+        mem_requests,
+        ..Default::default()
+    }
+}
+
+fn taint(key: &str, value: Option<&str>, effect: &str) -> Taint {
+    Taint {
+        key: String::from(key),
+        value: value.map(String::from),
+        effect: String::from(effect),
+        time_added: None,
+    }
+}
+
+#[test]
+fn test_node_state_check_resources() {
+    let n = node("worker", (4000, 8192.0, 10000.0, 110), (1000, 2048.0, 0.0, 10));
+
+    assert_eq!(n.check(&pod(1000, 1024.0)), Ok(3));
+    assert_eq!(n.check(&pod(500, 4096.0)), Ok(1));
+    assert_eq!(n.check(&pod(4000, 1024.0)), Err(String::from("insufficient cpu")));
+    assert_eq!(n.check(&pod(1000, 8192.0)), Err(String::from("insufficient memory")));
+
+    let full = node("worker", (4000, 8192.0, 10000.0, 10), (1000, 2048.0, 0.0, 10));
+    assert_eq!(full.check(&pod(100, 100.0)), Err(String::from("too many pods")));
+
+    let mut cordoned = n.clone();
+    cordoned.unschedulable = true;
+    assert_eq!(cordoned.check(&pod(100, 100.0)), Err(String::from("node is unschedulable")));
+}
+
+#[test]
+fn test_taints_and_tolerations() {
+    let taints = vec![taint("dedicated", Some("gpu"), "NoSchedule"), taint("soft", None, "PreferNoSchedule")];
+
+    assert!(get_untolerated_taint(&[], &taints).is_some());
+
+    let equal = Toleration {
+        key: Some(String::from("dedicated")),
+        operator: Some(String::from("Equal")),
+        value: Some(String::from("gpu")),
+        effect: Some(String::from("NoSchedule")),
+        ..Default::default()
+    };
+    assert!(get_untolerated_taint(std::slice::from_ref(&equal), &taints).is_none());
+
+    let wrong_value = Toleration { value: Some(String::from("cpu")), ..equal.clone() };
+    assert!(get_untolerated_taint(&[wrong_value], &taints).is_some());
+
+    let wrong_effect = Toleration { effect: Some(String::from("NoExecute")), ..equal };
+    assert!(get_untolerated_taint(&[wrong_effect], &taints).is_some());
+
+    let exists_all = Toleration { operator: Some(String::from("Exists")), ..Default::default() };
+    assert!(get_untolerated_taint(&[exists_all], &taints).is_none());
+
+    let mut n = node("gpu", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    n.taints = taints;
+    assert_eq!(n.check(&pod(100, 100.0)), Err(String::from("untolerated taint dedicated=gpu:NoSchedule")));
+}
+
+#[test]
+fn test_node_selector_and_affinity() {
+    let labels = BTreeMap::from([
+        (String::from("pool"), String::from("general")),
+        (String::from("cores"), String::from("8")),
+    ]);
+
+    assert!(matches_node_selector(&labels, &BTreeMap::new()));
+    assert!(matches_node_selector(&labels, &BTreeMap::from([(String::from("pool"), String::from("general"))])));
+    assert!(!matches_node_selector(&labels, &BTreeMap::from([(String::from("pool"), String::from("gpu"))])));
+
+    let requirement = |key: &str, operator: &str, values: &[&str]| NodeSelectorRequirement {
+        key: String::from(key),
+        operator: String::from(operator),
+        values: Some(values.iter().map(|v| String::from(*v)).collect()),
+    };
+    let affinity = |expressions: Vec<NodeSelectorRequirement>| NodeSelector {
+        node_selector_terms: vec![NodeSelectorTerm { match_expressions: Some(expressions), match_fields: None }],
+    };
+
+    assert!(matches_node_affinity("worker", &labels, &affinity(vec![requirement("pool", "In", &["general", "spot"])])));
+    assert!(!matches_node_affinity("worker", &labels, &affinity(vec![requirement("pool", "NotIn", &["general"])])));
+    assert!(matches_node_affinity("worker", &labels, &affinity(vec![requirement("zone", "DoesNotExist", &[])])));
+    assert!(matches_node_affinity("worker", &labels, &affinity(vec![requirement("cores", "Gt", &["4"])])));
+    assert!(!matches_node_affinity("worker", &labels, &affinity(vec![requirement("cores", "Lt", &["4"])])));
+    assert!(!matches_node_affinity("worker", &labels, &affinity(vec![
+        requirement("pool", "Exists", &[]),
+        requirement("zone", "Exists", &[]),
+    ])));
+
+    let by_name = NodeSelector {
+        node_selector_terms: vec![NodeSelectorTerm {
+            match_expressions: None,
+            match_fields: Some(vec![requirement("metadata.name", "In", &["worker"])]),
+        }],
+    };
+    assert!(matches_node_affinity("worker", &labels, &by_name));
+    assert!(!matches_node_affinity("worker2", &labels, &by_name));
+}
+
+#[test]
+fn test_get_fragmentation() {
+    let nodes = [
+        node("worker", (4000, 8192.0, 0.0, 110), (3500, 4096.0, 0.0, 10)),
+        node("worker2", (4000, 8192.0, 0.0, 110), (3500, 6144.0, 0.0, 10)),
+        node("worker3", (4000, 8192.0, 0.0, 110), (2000, 8192.0, 0.0, 10)),
+        node("worker4", (4000, 8192.0, 0.0, 10), (0, 0.0, 0.0, 10)),
+    ];
+    let refs: Vec<&NodeState> = nodes.iter().collect();
+
+    // worker3 has the most free cpu but no free memory, worker has the most free memory
+    let f = get_fragmentation(&refs, false);
+    assert_eq!(f.nodes, 4);
+    assert_eq!(f.cpu_free, 3000);
+    assert_eq!(f.cpu_largest, 2000);
+    assert_eq!(f.mem_free, 6144.0);
+    assert_eq!(f.mem_largest, 0.0);
+    assert!((f.index - (2.0 / 3.0)).abs() < 0.0001);
+
+    let f = get_fragmentation(&refs, true);
+    assert_eq!(f.cpu_largest, 500);
+    assert_eq!(f.mem_largest, 4096.0);
+    assert!((f.index - (7.0 / 12.0)).abs() < 0.0001);
+
+    let f = get_fragmentation(&refs[2..3], false);
+    assert_eq!(f.index, 0.0);
+}
+
+#[test]
+fn test_simulate_drain() {
+    let mut nodes = vec![
+        node("worker", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+        node("worker2", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+        node("worker3", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+    ];
+
+    let scheduled = |node: &str, cpu: u32, daemonset: bool| ScheduledPod {
+        node: String::from(node),
+        daemonset,
+        mirror: false,
+        shape: PodShape { name: format!("default/{}-{}", node, cpu), ..pod(cpu, 512.0) },
+    };
+    let pods = vec![
+        scheduled("worker", 3000, false),
+        scheduled("worker", 2000, false),
+        scheduled("worker", 100, true),
+        scheduled("worker2", 1500, false),
+        scheduled("worker3", 1000, false),
+    ];
+    for p in &pods {
+        nodes.iter_mut().find(|n| n.name == p.node).unwrap().place(&p.shape);
+    }
+
+    let placements = simulate_drain(&mut nodes, &pods, &[String::from("worker")]);
+
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(placements.len(), 2);
+    assert_eq!(placements[0].0.shape.name, "default/worker-3000");
+    assert_eq!(placements[0].1, Ok(String::from("worker3")));
+    assert_eq!(placements[1].0.shape.name, "default/worker-2000");
+    assert_eq!(placements[1].1, Ok(String::from("worker2")));
+
+    let placements = simulate_drain(&mut nodes, &pods, &[String::from("worker3")]);
+    assert_eq!(placements.len(), 1);
+    assert_eq!(placements[0].1, Err(String::from("0/1 nodes are available: 1 insufficient cpu")));
+}
+
+#[test]
+fn test_consolidate() {
+    let mut nodes = vec![
+        node("worker", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+        node("worker2", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+        node("worker3", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+    ];
+
+    let scheduled = |node: &str, name: &str, cpu: u32, daemonset: bool| ScheduledPod {
+        node: String::from(node),
+        daemonset,
+        mirror: false,
+        shape: PodShape { name: String::from(name), ..pod(cpu, 1024.0) },
+    };
+    let mut pods = vec![
+        scheduled("worker", "default/a", 2500, false),
+        scheduled("worker2", "default/b", 1000, false),
+        scheduled("worker2", "kube-system/ds-1", 100, true),
+        scheduled("worker3", "default/c", 3000, false),
+    ];
+    for p in &pods {
+        nodes.iter_mut().find(|n| n.name == p.node).unwrap().place(&p.shape);
+    }
+
+    let removed = consolidate(&mut nodes, &mut pods);
+
+    assert_eq!(removed, vec![String::from("worker2")]);
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(pods.len(), 3);
+    assert_eq!(pods.iter().find(|p| p.shape.name == "default/b").unwrap().node, "worker");
+
+    let (cpu_percentage, mem_percentage) = get_requests_percentage(&nodes);
+    assert_eq!(cpu_percentage, 81.25);
+    assert_eq!(mem_percentage, 18.75);
+}
+
+#[test]
+fn test_consolidate_keeps_fixed_nodes() {
+    let mut cp = node("cp-1", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    cp.taints.push(taint("node-role.kubernetes.io/control-plane", None, "NoSchedule"));
+    let mut infra = node("infra", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    infra.labels.insert(String::from("node-role.kubernetes.io/master"), String::new());
+    let mut cordoned = node("cordoned", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    cordoned.unschedulable = true;
+
+    let mut nodes = vec![
+        cp,
+        infra,
+        cordoned,
+        node("edge", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+        node("worker", (4000, 8192.0, 0.0, 110), (0, 0.0, 0.0, 0)),
+    ];
+    let mut pods = vec![ScheduledPod {
+        node: String::from("edge"),
+        daemonset: false,
+        mirror: true,
+        shape: PodShape { name: String::from("kube-system/haproxy-edge"), ..pod(100, 128.0) },
+    }];
+
+    let removed = consolidate(&mut nodes, &mut pods);
+
+    assert_eq!(removed, vec![String::from("worker")]);
+    assert_eq!(get_requests_percentage(&[]), (0.0, 0.0));
+}
+
+#[test]
+fn test_simulate_scale() {
+    let mut small = node("small", (2000, 4096.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    small.labels.insert(String::from("node.kubernetes.io/instance-type"), String::from("m5.large"));
+    let mut large = node("large", (8000, 16384.0, 0.0, 110), (0, 0.0, 0.0, 0));
+    large.labels.insert(String::from("node.kubernetes.io/instance-type"), String::from("m5.2xlarge"));
+
+    let daemonset = ScheduledPod {
+        node: String::from("large"),
+        daemonset: true,
+        mirror: false,
+        shape: pod(1000, 512.0),
+    };
+    large.place(&daemonset.shape);
+
+    let nodes = vec![small, large];
+    let p = pod(2000, 2048.0);
+
+    let mut trial = nodes.clone();
+    let (placed, pending) = simulate_scale(&mut trial, &p, 6);
+    assert_eq!(placed, BTreeMap::from([(String::from("large"), 3), (String::from("small"), 1)]));
+    assert_eq!(pending, 2);
+
+    let needed = get_nodes_needed(&nodes, &[daemonset], &p, pending);
+    assert_eq!(needed, vec![
+        (String::from("m5.2xlarge"), Ok((3, 1))),
+        (String::from("m5.large"), Ok((1, 2))),
+    ]);
+}
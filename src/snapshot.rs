@@ -0,0 +1,247 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use k8s_openapi::api::{apps::v1::{Deployment, StatefulSet}, core::v1::{Namespace, Node, Pod}};
+use kube::api::ListParams;
+use serde::Deserialize;
+
+use super::{error::Error, kubernetes::{NodeMetrics, PodMetrics}, source::DataSource};
+
+#[derive(Default)]
+pub struct Snapshot {
+    pub nodes: Vec<Node>,
+    pub pods: Vec<Pod>,
+    pub namespaces: Vec<Namespace>,
+    pub node_metrics: Vec<NodeMetrics>,
+    pub pod_metrics: Vec<PodMetrics>,
+    pub deployments: Vec<Deployment>,
+    pub statefulsets: Vec<StatefulSet>,
+}
+
+impl Snapshot {
+    pub fn load(path: &str) -> Result<Snapshot, Error> {
+        let mut snapshot = Snapshot::default();
+        let path = Path::new(path);
+
+        if path.is_dir() {
+            let mut files = match fs::read_dir(path) {
+                Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect::<Vec<_>>(),
+                Err(e) => return Err(Error::from_io(e, format!("Error reading {}", path.display()))),
+            };
+            files.sort();
+
+            for file in files {
+                let extension = file.extension().and_then(|e| e.to_str()).unwrap_or_default();
+                if matches!(extension, "json" | "yaml" | "yml") {
+                    snapshot.load_file(&file)?;
+                }
+            }
+        } else {
+            snapshot.load_file(path)?;
+        }
+
+        Ok(snapshot)
+    }
+
+    fn load_file(&mut self, path: &Path) -> Result<(), Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => return Err(Error::from_io(e, format!("Error reading {}", path.display()))),
+        };
+
+        self.parse(&content).map_err(|e| Error::Parse(format!("{} in {}", e, path.display())))
+    }
+
+    pub fn parse(&mut self, content: &str) -> Result<(), Error> {
+        for document in serde_yaml::Deserializer::from_str(content) {
+            let value = match serde_yaml::Value::deserialize(document) {
+                Ok(value) => value,
+                Err(e) => return Err(Error::Parse(format!("Error parsing snapshot {}", e))),
+            };
+
+            self.add_object(value, "")?;
+        }
+
+        Ok(())
+    }
+
+    fn add_object(&mut self, value: serde_yaml::Value, default_kind: &str) -> Result<(), Error> {
+        let kind = value.get("kind").and_then(|k| k.as_str()).unwrap_or(default_kind).to_string();
+        let parse_error = |e: serde_yaml::Error| Error::Parse(format!("Error parsing {} {}", kind, e));
+
+        match kind.as_str() {
+            "Node" => self.nodes.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "Pod" => self.pods.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "Namespace" => self.namespaces.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "NodeMetrics" => self.node_metrics.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "PodMetrics" => self.pod_metrics.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "Deployment" => self.deployments.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            "StatefulSet" => self.statefulsets.push(serde_yaml::from_value(value).map_err(parse_error)?),
+            _ if kind.ends_with("List") => {
+                let item_kind = kind.trim_end_matches("List");
+                if let Some(items) = value.get("items").and_then(|i| i.as_sequence()) {
+                    for item in items {
+                        self.add_object(item.clone(), item_kind)?;
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+}
+
+impl DataSource for Snapshot {
+    async fn list_nodes(&self, lp: &ListParams) -> Result<Vec<Node>, Error> {
+        Ok(self.nodes.iter()
+            .filter(|n| matches_list_params(&n.metadata, &BTreeMap::new(), lp))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_node(&self, name: &str) -> Result<Node, Error> {
+        self.nodes.iter()
+            .find(|n| n.metadata.name.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("node {} not found", name)))
+    }
+
+    async fn list_pods(&self, lp: &ListParams) -> Result<Vec<Pod>, Error> {
+        Ok(self.pods.iter()
+            .filter(|p| {
+                let mut fields = BTreeMap::new();
+                if let Some(node_name) = p.spec.as_ref().and_then(|s| s.node_name.clone()) {
+                    fields.insert("spec.nodeName", node_name);
+                }
+                if let Some(phase) = p.status.as_ref().and_then(|s| s.phase.clone()) {
+                    fields.insert("status.phase", phase);
+                }
+                matches_list_params(&p.metadata, &fields, lp)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn list_namespaces(&self, lp: &ListParams) -> Result<Vec<Namespace>, Error> {
+        Ok(self.namespaces.iter()
+            .filter(|n| matches_list_params(&n.metadata, &BTreeMap::new(), lp))
+            .cloned()
+            .collect())
+    }
+
+    async fn get_node_metrics(&self, name: &str) -> Result<NodeMetrics, Error> {
+        self.node_metrics.iter()
+            .find(|m| m.metadata.name.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| Error::MetricsUnavailable(format!("node metrics for {} not found", name)))
+    }
+
+    async fn list_pod_metrics(&self, namespace: Option<&str>) -> Result<Vec<PodMetrics>, Error> {
+        Ok(self.pod_metrics.iter()
+            .filter(|m| namespace.is_none() || m.metadata.namespace.as_deref() == namespace)
+            .cloned()
+            .collect())
+    }
+
+    async fn get_deployment(&self, namespace: &str, name: &str) -> Result<Deployment, Error> {
+        self.deployments.iter()
+            .find(|d| d.metadata.namespace.as_deref() == Some(namespace) && d.metadata.name.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("deployment {}/{} not found", namespace, name)))
+    }
+
+    async fn get_statefulset(&self, namespace: &str, name: &str) -> Result<StatefulSet, Error> {
+        self.statefulsets.iter()
+            .find(|s| s.metadata.namespace.as_deref() == Some(namespace) && s.metadata.name.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| Error::NotFound(format!("statefulset {}/{} not found", namespace, name)))
+    }
+
+    fn metrics_available(&self) -> Result<(), Error> {
+        match self.node_metrics.is_empty() && self.pod_metrics.is_empty() {
+            true => Err(Error::MetricsUnavailable(String::from("the snapshot has no NodeMetrics or PodMetrics, usage is unknown"))),
+            false => Ok(()),
+        }
+    }
+}
+
+fn matches_list_params(metadata: &kube::core::ObjectMeta, extra_fields: &BTreeMap<&str, String>, lp: &ListParams) -> bool {
+    if let Some(selector) = &lp.label_selector {
+        if !matches_label_selector(&metadata.labels.clone().unwrap_or_default(), selector) {
+            return false;
+        }
+    }
+
+    if let Some(selector) = &lp.field_selector {
+        let mut fields = extra_fields.clone();
+        fields.insert("metadata.name", metadata.name.clone().unwrap_or_default());
+        fields.insert("metadata.namespace", metadata.namespace.clone().unwrap_or_default());
+
+        if !matches_field_selector(&fields, selector) {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn split_requirements(selector: &str) -> Vec<&str> {
+    let mut requirements = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in selector.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                requirements.push(selector[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    requirements.push(selector[start..].trim());
+
+    requirements.into_iter().filter(|r| !r.is_empty()).collect()
+}
+
+fn parse_set(values: &str) -> Vec<&str> {
+    values.trim().trim_start_matches('(').trim_end_matches(')').split(',').map(|v| v.trim()).collect()
+}
+
+pub fn matches_label_selector(labels: &BTreeMap<String, String>, selector: &str) -> bool {
+    split_requirements(selector).into_iter().all(|requirement| {
+        if let Some((key, values)) = requirement.split_once(" notin ") {
+            match labels.get(key.trim()) {
+                Some(value) => !parse_set(values).contains(&value.as_str()),
+                None => true,
+            }
+        } else if let Some((key, values)) = requirement.split_once(" in ") {
+            match labels.get(key.trim()) {
+                Some(value) => parse_set(values).contains(&value.as_str()),
+                None => false,
+            }
+        } else if let Some((key, value)) = requirement.split_once("!=") {
+            labels.get(key.trim()).map(|v| v != value.trim()).unwrap_or(true)
+        } else if let Some((key, value)) = requirement.split_once("==").or_else(|| requirement.split_once('=')) {
+            labels.get(key.trim()).map(|v| v == value.trim()).unwrap_or(false)
+        } else if let Some(key) = requirement.strip_prefix('!') {
+            !labels.contains_key(key.trim())
+        } else {
+            labels.contains_key(requirement)
+        }
+    })
+}
+
+pub fn matches_field_selector(fields: &BTreeMap<&str, String>, selector: &str) -> bool {
+    split_requirements(selector).into_iter().all(|requirement| {
+        if let Some((key, value)) = requirement.split_once("!=") {
+            fields.get(key.trim()).map(|v| v != value.trim()).unwrap_or(true)
+        } else if let Some((key, value)) = requirement.split_once("==").or_else(|| requirement.split_once('=')) {
+            fields.get(key.trim()).map(|v| v == value.trim()).unwrap_or(false)
+        } else {
+            false
+        }
+    })
+}
@@ -0,0 +1,98 @@
+use std::collections::BTreeMap;
+
+use kube::api::ListParams;
+
+use super::{snapshot::*, source::DataSource};
+
+#[test]
+fn test_matches_label_selector() {
+    let labels = BTreeMap::from([
+        (String::from("role"), String::from("worker")),
+        (String::from("zone"), String::from("a")),
+    ]);
+
+    assert!(matches_label_selector(&labels, "role=worker"));
+    assert!(matches_label_selector(&labels, "role==worker,zone"));
+    assert!(matches_label_selector(&labels, "role!=infra,!gpu"));
+    assert!(matches_label_selector(&labels, "zone in (a, b),role notin (infra)"));
+    assert!(!matches_label_selector(&labels, "zone in (b,c)"));
+    assert!(!matches_label_selector(&labels, "gpu"));
+    assert!(!matches_label_selector(&labels, "role=infra"));
+}
+
+#[tokio::test]
+async fn test_parse_snapshot() {
+    let content = r#"
+{
+  "apiVersion": "v1",
+  "kind": "List",
+  "items": [
+    {"apiVersion": "v1", "kind": "Node", "metadata": {"name": "node-1", "labels": {"role": "worker"}}},
+    {"apiVersion": "v1", "kind": "Node", "metadata": {"name": "node-2", "labels": {"role": "infra"}}}
+  ]
+}
+---
+apiVersion: v1
+kind: PodList
+items:
+- metadata:
+    name: web
+    namespace: default
+  spec:
+    nodeName: node-1
+    containers:
+    - name: web
+- metadata:
+    name: db
+    namespace: data
+  spec:
+    nodeName: node-2
+    containers:
+    - name: db
+---
+apiVersion: metrics.k8s.io/v1beta1
+kind: NodeMetricsList
+items:
+- metadata:
+    name: node-1
+  timestamp: "2023-01-01T00:00:00Z"
+  window: 30s
+  usage:
+    cpu: 250m
+    memory: 1Gi
+---
+apiVersion: metrics.k8s.io/v1beta1
+kind: PodMetrics
+metadata:
+  name: db
+  namespace: data
+containers:
+- name: db
+  usage:
+    cpu: 100m
+    memory: 512Mi
+"#;
+
+    let mut snapshot = Snapshot::default();
+    snapshot.parse(content).unwrap();
+
+    assert_eq!(snapshot.nodes.len(), 2);
+    assert_eq!(snapshot.pods.len(), 2);
+    assert_eq!(snapshot.node_metrics.len(), 1);
+
+    let nodes = snapshot.list_nodes(&ListParams::default().labels("role=worker")).await.unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].metadata.name.as_deref(), Some("node-1"));
+
+    let pods = snapshot.list_pods(&ListParams::default().fields("spec.nodeName=node-2")).await.unwrap();
+    assert_eq!(pods.len(), 1);
+    assert_eq!(pods[0].metadata.name.as_deref(), Some("db"));
+
+    let pods = snapshot.list_pods(&ListParams::default().fields("metadata.namespace=default")).await.unwrap();
+    assert_eq!(pods.len(), 1);
+    assert_eq!(pods[0].metadata.name.as_deref(), Some("web"));
+
+    assert_eq!(snapshot.list_pod_metrics(None).await.unwrap().len(), 1);
+    assert_eq!(snapshot.list_pod_metrics(Some("data")).await.unwrap().len(), 1);
+    assert!(snapshot.list_pod_metrics(Some("default")).await.unwrap().is_empty());
+}
@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, future::Future, sync::{Arc, Mutex, PoisonError}, time::{Duration, Instant}};
+
+use k8s_openapi::{api::{apps::v1::{Deployment, StatefulSet}, core::v1::{Namespace, Node, Pod}}, http};
+use kube::{api::ListParams, Api, Client};
+
+use super::{error::Error, kubernetes::{NodeMetrics, PodMetrics}, prometheus::Prometheus, snapshot::Snapshot, summary::Summary};
+
+/// cluster objects read by the aggregation functions, served by the api client or a local snapshot
+pub trait DataSource {
+    fn list_nodes(&self, lp: &ListParams) -> impl Future<Output = Result<Vec<Node>, Error>> + Send;
+    fn get_node(&self, name: &str) -> impl Future<Output = Result<Node, Error>> + Send;
+    fn list_pods(&self, lp: &ListParams) -> impl Future<Output = Result<Vec<Pod>, Error>> + Send;
+    fn list_namespaces(&self, lp: &ListParams) -> impl Future<Output = Result<Vec<Namespace>, Error>> + Send;
+    fn get_node_metrics(&self, name: &str) -> impl Future<Output = Result<NodeMetrics, Error>> + Send;
+    /// metrics of the pods in `namespace`, or in every namespace
+    fn list_pod_metrics(&self, namespace: Option<&str>) -> impl Future<Output = Result<Vec<PodMetrics>, Error>> + Send;
+    /// highest usage of the pods over a window, none when the source only has current usage
+    fn list_pod_peak_metrics(&self, _namespace: Option<&str>) -> impl Future<Output = Result<Option<Vec<PodMetrics>>, Error>> + Send {
+        async { Ok(None) }
+    }
+    fn get_deployment(&self, namespace: &str, name: &str) -> impl Future<Output = Result<Deployment, Error>> + Send;
+    fn get_statefulset(&self, namespace: &str, name: &str) -> impl Future<Output = Result<StatefulSet, Error>> + Send;
+    /// whether usage can be read at all, checked once before asking for metrics of each object
+    fn metrics_available(&self) -> Result<(), Error>;
+    /// data that couldn't be read while listing, eg: the summary of an unreachable kubelet
+    fn partial_errors(&self) -> Vec<Error> {
+        Vec::new()
+    }
+}
+
+/// the kubelet refreshes its stats every ~10s, reading a summary again sooner only repeats it
+const SUMMARY_TTL: Duration = Duration::from_secs(5);
+
+/// kubelet summaries by node, with the time they were read
+type SummaryCache = BTreeMap<String, (Instant, Result<Summary, Error>)>;
+
+/// where usage of nodes and pods is read from
+#[derive(Clone)]
+pub enum MetricsSource {
+    MetricsServer,
+    Kubelet,
+    Prometheus(Prometheus),
+}
+
+#[derive(Clone)]
+enum Metrics {
+    /// served version of the metrics.k8s.io api
+    Api(String),
+    Kubelet,
+    Prometheus(Box<Prometheus>),
+}
+
+/// the cluster api, with usage from the metrics api found by discovery, the kubelet summary api or prometheus
+#[derive(Clone)]
+pub struct ApiSource {
+    client: Client,
+    metrics: Result<Metrics, Error>,
+    summaries: Arc<Mutex<SummaryCache>>,
+    partial_errors: Arc<Mutex<Vec<Error>>>,
+}
+
+impl ApiSource {
+    pub async fn new(client: Client, metrics_source: MetricsSource) -> ApiSource {
+        let metrics = match metrics_source {
+            MetricsSource::Kubelet => Ok(Metrics::Kubelet),
+            MetricsSource::Prometheus(prometheus) => Ok(Metrics::Prometheus(Box::new(prometheus))),
+            MetricsSource::MetricsServer => match client.list_api_groups().await {
+                Ok(groups) => groups.groups.into_iter()
+                    .find(|g| g.name == "metrics.k8s.io")
+                    .and_then(|g| g.preferred_version.or_else(|| g.versions.into_iter().next()))
+                    .map(|v| Metrics::Api(v.version))
+                    .ok_or_else(|| Error::MetricsUnavailable(String::from("metrics.k8s.io api is not served by the cluster, usage is unknown (is metrics-server installed? try --metrics-source kubelet)"))),
+                Err(e) => Err(Error::from_metrics(e).context("Error discovering metrics.k8s.io api")),
+            },
+        };
+
+        ApiSource { client, metrics, summaries: Arc::default(), partial_errors: Arc::default() }
+    }
+
+    fn metrics(&self) -> Result<&Metrics, Error> {
+        self.metrics.as_ref().map_err(|e| e.clone())
+    }
+
+    pub async fn get_node_summary(&self, name: &str) -> Result<Summary, Error> {
+        let request = http::Request::get(format!("/api/v1/nodes/{}/proxy/stats/summary", name))
+            .body(Vec::new())
+            .map_err(|e| Error::Api(e.to_string()))?;
+
+        self.client.request::<Summary>(request).await
+            .map_err(|e| Error::from_metrics(e).context(&format!("Error getting kubelet summary of {}", name)))
+    }
+
+    /// the summary of a node, read once per SUMMARY_TTL however many rows ask for it
+    async fn get_cached_summary(&self, name: &str) -> Result<Summary, Error> {
+        if let Some((read, summary)) = self.summaries.lock().unwrap_or_else(PoisonError::into_inner).get(name) {
+            if read.elapsed() < SUMMARY_TTL {
+                return summary.clone();
+            }
+        }
+
+        let summary = self.get_node_summary(name).await;
+        self.summaries.lock().unwrap_or_else(PoisonError::into_inner).insert(name.to_string(), (Instant::now(), summary.clone()));
+        summary
+    }
+}
+
+impl DataSource for ApiSource {
+    async fn list_nodes(&self, lp: &ListParams) -> Result<Vec<Node>, Error> {
+        Api::<Node>::all(self.client.clone()).list(lp).await.map(|l| l.items).map_err(Error::from)
+    }
+
+    async fn get_node(&self, name: &str) -> Result<Node, Error> {
+        Api::<Node>::all(self.client.clone()).get(name).await.map_err(Error::from)
+    }
+
+    async fn list_pods(&self, lp: &ListParams) -> Result<Vec<Pod>, Error> {
+        Api::<Pod>::all(self.client.clone()).list(lp).await.map(|l| l.items).map_err(Error::from)
+    }
+
+    async fn list_namespaces(&self, lp: &ListParams) -> Result<Vec<Namespace>, Error> {
+        Api::<Namespace>::all(self.client.clone()).list(lp).await.map(|l| l.items).map_err(Error::from)
+    }
+
+    async fn get_node_metrics(&self, name: &str) -> Result<NodeMetrics, Error> {
+        match self.metrics()? {
+            Metrics::Api(version) => Api::<NodeMetrics>::all_with(self.client.clone(), version).get(name).await.map_err(Error::from_metrics),
+            Metrics::Kubelet => Ok(self.get_cached_summary(name).await?.node_metrics()),
+            Metrics::Prometheus(prometheus) => prometheus.get_node_metrics(name).await,
+        }
+    }
+
+    async fn list_pod_metrics(&self, namespace: Option<&str>) -> Result<Vec<PodMetrics>, Error> {
+        match self.metrics()? {
+            Metrics::Api(version) => {
+                let api = match namespace {
+                    Some(namespace) => Api::<PodMetrics>::namespaced_with(self.client.clone(), namespace, version),
+                    None => Api::<PodMetrics>::all_with(self.client.clone(), version),
+                };
+                api.list(&ListParams::default()).await.map(|l| l.items).map_err(Error::from_metrics)
+            },
+            Metrics::Kubelet => {
+                let nodes = self.list_nodes(&ListParams::default()).await?;
+
+                let mut pod_metrics = Vec::new();
+                let mut failed = Vec::new();
+                for node in &nodes {
+                    match self.get_cached_summary(node.metadata.name.as_deref().unwrap_or_default()).await {
+                        Ok(summary) => pod_metrics.extend(summary.pod_metrics().into_iter().filter(|m| namespace.is_none() || m.metadata.namespace.as_deref() == namespace)),
+                        Err(e) => failed.push(e),
+                    }
+                }
+
+                // pods on the nodes that answered still have usage, the others are reported once
+                if !nodes.is_empty() && failed.len() == nodes.len() {
+                    return Err(failed.remove(0));
+                }
+
+                let mut partial_errors = self.partial_errors.lock().unwrap_or_else(PoisonError::into_inner);
+                for e in failed {
+                    if !partial_errors.contains(&e) {
+                        partial_errors.push(e);
+                    }
+                }
+                Ok(pod_metrics)
+            },
+            Metrics::Prometheus(prometheus) => prometheus.list_pod_metrics(namespace).await,
+        }
+    }
+
+    async fn list_pod_peak_metrics(&self, namespace: Option<&str>) -> Result<Option<Vec<PodMetrics>>, Error> {
+        match self.metrics()? {
+            Metrics::Prometheus(prometheus) => prometheus.list_pod_peak_metrics(namespace).await,
+            _ => Ok(None),
+        }
+    }
+
+    async fn get_deployment(&self, namespace: &str, name: &str) -> Result<Deployment, Error> {
+        Api::<Deployment>::namespaced(self.client.clone(), namespace).get(name).await.map_err(Error::from)
+    }
+
+    async fn get_statefulset(&self, namespace: &str, name: &str) -> Result<StatefulSet, Error> {
+        Api::<StatefulSet>::namespaced(self.client.clone(), namespace).get(name).await.map_err(Error::from)
+    }
+
+    fn metrics_available(&self) -> Result<(), Error> {
+        self.metrics().map(|_| ())
+    }
+
+    fn partial_errors(&self) -> Vec<Error> {
+        self.partial_errors.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+/// data source selected at runtime, either the cluster api or a local dump
+pub enum Source {
+    Live(ApiSource),
+    Offline(Arc<Snapshot>),
+}
+
+impl DataSource for Source {
+    async fn list_nodes(&self, lp: &ListParams) -> Result<Vec<Node>, Error> {
+        match self {
+            Source::Live(client) => client.list_nodes(lp).await,
+            Source::Offline(snapshot) => snapshot.list_nodes(lp).await,
+        }
+    }
+
+    async fn get_node(&self, name: &str) -> Result<Node, Error> {
+        match self {
+            Source::Live(client) => client.get_node(name).await,
+            Source::Offline(snapshot) => snapshot.get_node(name).await,
+        }
+    }
+
+    async fn list_pods(&self, lp: &ListParams) -> Result<Vec<Pod>, Error> {
+        match self {
+            Source::Live(client) => client.list_pods(lp).await,
+            Source::Offline(snapshot) => snapshot.list_pods(lp).await,
+        }
+    }
+
+    async fn list_namespaces(&self, lp: &ListParams) -> Result<Vec<Namespace>, Error> {
+        match self {
+            Source::Live(client) => client.list_namespaces(lp).await,
+            Source::Offline(snapshot) => snapshot.list_namespaces(lp).await,
+        }
+    }
+
+    async fn get_node_metrics(&self, name: &str) -> Result<NodeMetrics, Error> {
+        match self {
+            Source::Live(client) => client.get_node_metrics(name).await,
+            Source::Offline(snapshot) => snapshot.get_node_metrics(name).await,
+        }
+    }
+
+    async fn list_pod_metrics(&self, namespace: Option<&str>) -> Result<Vec<PodMetrics>, Error> {
+        match self {
+            Source::Live(client) => client.list_pod_metrics(namespace).await,
+            Source::Offline(snapshot) => snapshot.list_pod_metrics(namespace).await,
+        }
+    }
+
+    async fn list_pod_peak_metrics(&self, namespace: Option<&str>) -> Result<Option<Vec<PodMetrics>>, Error> {
+        match self {
+            Source::Live(client) => client.list_pod_peak_metrics(namespace).await,
+            Source::Offline(snapshot) => snapshot.list_pod_peak_metrics(namespace).await,
+        }
+    }
+
+    async fn get_deployment(&self, namespace: &str, name: &str) -> Result<Deployment, Error> {
+        match self {
+            Source::Live(client) => client.get_deployment(namespace, name).await,
+            Source::Offline(snapshot) => snapshot.get_deployment(namespace, name).await,
+        }
+    }
+
+    async fn get_statefulset(&self, namespace: &str, name: &str) -> Result<StatefulSet, Error> {
+        match self {
+            Source::Live(client) => client.get_statefulset(namespace, name).await,
+            Source::Offline(snapshot) => snapshot.get_statefulset(namespace, name).await,
+        }
+    }
+
+    fn metrics_available(&self) -> Result<(), Error> {
+        match self {
+            Source::Live(api) => api.metrics_available(),
+            Source::Offline(snapshot) => snapshot.metrics_available(),
+        }
+    }
+
+    fn partial_errors(&self) -> Vec<Error> {
+        match self {
+            Source::Live(api) => api.partial_errors(),
+            Source::Offline(snapshot) => snapshot.partial_errors(),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+use kube::{Client, Config};
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::TcpListener};
+
+use super::{error::Error, source::*};
+
+const NODES: &str = r#"{"apiVersion":"v1","kind":"NodeList","metadata":{},"items":[{"metadata":{"name":"node-1"}},{"metadata":{"name":"node-2"}}]}"#;
+
+const SUMMARY: &str = r#"{"node":{"nodeName":"node-1"},"pods":[
+  {"podRef":{"name":"web","namespace":"default"},"containers":[{"name":"web","cpu":{"usageNanoCores":250000000},"memory":{"workingSetBytes":314572800}}]},
+  {"podRef":{"name":"db","namespace":"data"},"containers":[{"name":"db","cpu":{"usageNanoCores":100000000},"memory":{"workingSetBytes":104857600}}]}
+]}"#;
+
+const UNAVAILABLE: &str = r#"{"apiVersion":"v1","kind":"Status","metadata":{},"status":"Failure","message":"kubelet unreachable","reason":"ServiceUnavailable","code":503}"#;
+
+/// serves the node list, a summary for node-1 and 503 for node-2, counting summary requests
+async fn stub(summaries: Arc<AtomicUsize>) -> Client {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut buf = vec![0; 8192];
+            let n = stream.read(&mut buf).await.unwrap();
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request.split_whitespace().nth(1).unwrap_or_default().to_string();
+
+            if path.ends_with("/proxy/stats/summary") {
+                summaries.fetch_add(1, Ordering::SeqCst);
+            }
+            let (status, body) = match path.as_str() {
+                p if p.starts_with("/api/v1/nodes?") || p == "/api/v1/nodes" => ("200 OK", NODES),
+                "/api/v1/nodes/node-1/proxy/stats/summary" => ("200 OK", SUMMARY),
+                _ => ("503 Service Unavailable", UNAVAILABLE),
+            };
+            let response = format!("HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", status, body.len(), body);
+            stream.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+
+    Client::try_from(Config::new(url.parse().unwrap())).unwrap()
+}
+
+#[tokio::test]
+async fn test_kubelet_summaries() {
+    let summaries = Arc::new(AtomicUsize::new(0));
+    let source = ApiSource::new(stub(summaries.clone()).await, MetricsSource::Kubelet).await;
+
+    let pods = source.list_pod_metrics(Some("default")).await.unwrap();
+    assert_eq!(pods.len(), 1);
+    assert_eq!(pods[0].metadata.name.as_deref(), Some("web"));
+
+    let pods = source.list_pod_metrics(Some("data")).await.unwrap();
+    assert_eq!(pods.len(), 1);
+
+    let node = source.get_node_metrics("node-1").await.unwrap();
+    assert_eq!(node.metadata.name.as_deref(), Some("node-1"));
+
+    assert_eq!(summaries.load(Ordering::SeqCst), 2);
+    assert!(matches!(source.partial_errors()[..], [Error::MetricsUnavailable(ref m)] if m.contains("node-2")));
+}
@@ -0,0 +1,144 @@
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use kube::core::ObjectMeta;
+use serde::Deserialize;
+
+use super::kubernetes::{ContainerMetrics, NodeMetrics, PodMetrics, Usage};
+
+/// response of the kubelet summary api, /api/v1/nodes/{node}/proxy/stats/summary
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct Summary {
+    pub node: NodeStats,
+    #[serde(default)]
+    pub pods: Vec<PodStats>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStats {
+    pub node_name: String,
+    pub cpu: Option<CpuStats>,
+    pub memory: Option<MemoryStats>,
+    pub fs: Option<FsStats>,
+    pub runtime: Option<RuntimeStats>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct CpuStats {
+    pub usage_nano_cores: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+    pub working_set_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FsStats {
+    pub used_bytes: Option<u64>,
+    pub capacity_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeStats {
+    pub image_fs: Option<FsStats>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct PodReference {
+    pub name: String,
+    pub namespace: String,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PodStats {
+    pub pod_ref: PodReference,
+    #[serde(default)]
+    pub containers: Vec<ContainerStats>,
+    #[serde(default)]
+    pub volume: Vec<VolumeStats>,
+    #[serde(rename = "ephemeral-storage")]
+    pub ephemeral_storage: Option<FsStats>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VolumeStats {
+    pub name: String,
+    pub used_bytes: Option<u64>,
+    pub capacity_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct ContainerStats {
+    pub name: String,
+    pub cpu: Option<CpuStats>,
+    pub memory: Option<MemoryStats>,
+}
+
+fn cpu_quantity(cpu: &Option<CpuStats>) -> Quantity {
+    Quantity(format!("{}n", cpu.as_ref().and_then(|c| c.usage_nano_cores).unwrap_or(0)))
+}
+
+fn memory_quantity(memory: &Option<MemoryStats>) -> Quantity {
+    Quantity(format!("{}", memory.as_ref().and_then(|m| m.working_set_bytes).unwrap_or(0)))
+}
+
+fn bytes_quantity(fs: &Option<FsStats>) -> Option<Quantity> {
+    fs.as_ref().and_then(|f| f.used_bytes).map(|b| Quantity(format!("{}", b)))
+}
+
+impl Summary {
+    pub fn node_metrics(&self) -> NodeMetrics {
+        let mut filesystems = Vec::new();
+        for (name, fs) in [("rootfs", &self.node.fs), ("imagefs", &self.node.runtime.as_ref().and_then(|r| r.image_fs.clone()))] {
+            if let Some(FsStats { used_bytes: Some(used), capacity_bytes: Some(capacity) }) = fs {
+                filesystems.push((name.to_string(), *used as f32 / 1024.0 / 1024.0, *capacity as f32 / 1024.0 / 1024.0));
+            }
+        }
+
+        let mut volumes = Vec::new();
+        for pod in &self.pods {
+            for volume in &pod.volume {
+                if let Some(used) = volume.used_bytes {
+                    volumes.push((
+                        format!("{}/{}", pod.pod_ref.namespace, pod.pod_ref.name),
+                        volume.name.clone(),
+                        used as f32 / 1024.0 / 1024.0,
+                        volume.capacity_bytes.map(|c| c as f32 / 1024.0 / 1024.0),
+                    ));
+                }
+            }
+        }
+
+        NodeMetrics {
+            metadata: ObjectMeta { name: Some(self.node.node_name.clone()), ..Default::default() },
+            usage: Usage {
+                cpu: cpu_quantity(&self.node.cpu),
+                memory: memory_quantity(&self.node.memory),
+                storage: bytes_quantity(&self.node.fs),
+            },
+            filesystems,
+            volumes,
+        }
+    }
+
+    pub fn pod_metrics(&self) -> Vec<PodMetrics> {
+        self.pods.iter().map(|pod| PodMetrics {
+            metadata: ObjectMeta {
+                name: Some(pod.pod_ref.name.clone()),
+                namespace: Some(pod.pod_ref.namespace.clone()),
+                ..Default::default()
+            },
+            containers: pod.containers.iter().map(|c| ContainerMetrics {
+                name: c.name.clone(),
+                usage: Usage { cpu: cpu_quantity(&c.cpu), memory: memory_quantity(&c.memory), storage: None },
+            }).collect(),
+            storage: bytes_quantity(&pod.ephemeral_storage),
+        }).collect()
+    }
+}
@@ -0,0 +1,47 @@
+use super::summary::*;
+
+#[test]
+fn test_summary_metrics() {
+    let content = r#"
+{
+  "node": {
+    "nodeName": "node-1",
+    "cpu": {"usageNanoCores": 1500000000},
+    "memory": {"workingSetBytes": 2147483648},
+    "fs": {"usedBytes": 5368709120, "capacityBytes": 21474836480},
+    "runtime": {"imageFs": {"usedBytes": 1073741824, "capacityBytes": 21474836480}}
+  },
+  "pods": [
+    {
+      "podRef": {"name": "web", "namespace": "default", "uid": "1"},
+      "containers": [
+        {"name": "web", "cpu": {"usageNanoCores": 250000000}, "memory": {"workingSetBytes": 314572800}}
+      ],
+      "volume": [{"name": "cache", "usedBytes": 104857600}, {"name": "data", "usedBytes": 524288000, "capacityBytes": 1073741824}, {"name": "token"}],
+      "ephemeral-storage": {"usedBytes": 209715200}
+    }
+  ]
+}
+"#;
+
+    let summary: Summary = serde_json::from_str(content).unwrap();
+
+    let node = summary.node_metrics();
+    assert_eq!(node.metadata.name.as_deref(), Some("node-1"));
+    assert_eq!(node.usage.cpu.0, "1500000000n");
+    assert_eq!(node.usage.storage.map(|s| s.0), Some(String::from("5368709120")));
+    assert_eq!(node.filesystems, vec![
+        (String::from("rootfs"), 5120.0, 20480.0),
+        (String::from("imagefs"), 1024.0, 20480.0),
+    ]);
+    assert_eq!(node.volumes, vec![
+        (String::from("default/web"), String::from("cache"), 100.0, None),
+        (String::from("default/web"), String::from("data"), 500.0, Some(1024.0)),
+    ]);
+
+    let pods = summary.pod_metrics();
+    assert_eq!(pods.len(), 1);
+    assert_eq!(pods[0].metadata.namespace.as_deref(), Some("default"));
+    assert_eq!(pods[0].containers[0].usage.memory.0, "314572800");
+    assert_eq!(pods[0].storage.as_ref().map(|s| s.0.as_str()), Some("209715200"));
+}
@@ -1,13 +1,17 @@
 use std::cmp::Reverse;
-use std::{str::FromStr};
+use std::{str::FromStr, collections::{BTreeMap, BTreeSet}};
 
-use super::kubernetes;
+use k8s_openapi::chrono::{DateTime, Utc};
+
+use super::{error::Error, kubernetes, scheduler};
 
 pub enum Filter {
     Cpu,
     Mem,
     Storage,
     Pods,
+    Fragmentation,
+    Efficiency,
     None,
 }
 
@@ -21,6 +25,8 @@ impl FromStr for Filter {
             "mem" => Ok(Filter::Mem),
             "storage" => Ok(Filter::Storage),
             "pods" => Ok(Filter::Pods),
+            "frag" => Ok(Filter::Fragmentation),
+            "eff" => Ok(Filter::Efficiency),
             _ => Err(format!("invalid filter {}", s))
         }
     }
@@ -43,37 +49,805 @@ pub fn parse_resource_data(rrs: Vec<kubernetes::ResouceRequests>, sort_by: Filte
             })
         },
         Filter::Pods => data.sort_by_key(|r| Reverse(r.pods)),
+        // least efficient first, by whichever of cpu and mem is lower, the cluster row stays last
+        Filter::Efficiency => {
+            let efficiency = |r: &kubernetes::ResouceRequests| {
+                let cpu = get_efficiency(r.cpu_usage.map(|u| u as f32), r.cpu_requests as f32);
+                let mem = get_efficiency(r.mem_usage, r.mem_requests);
+                match (cpu, mem) {
+                    (Some(c), Some(m)) => c.min(m),
+                    (Some(e), None) | (None, Some(e)) => e,
+                    (None, None) => f32::MAX,
+                }
+            };
+            data.sort_by(|a, b| (a.name == "*").cmp(&(b.name == "*")).then(efficiency(a).partial_cmp(&efficiency(b)).unwrap()))
+        },
         _ => (),
     }
 
+    let mut largest_cpu_free: u32 = 0;
+    let mut largest_mem_free: f32 = 0.0;
+    let mut largest_storage_free: f32 = 0.0;
+    let mut largest_pods_free: usize = 0;
+
+    for rr in data.iter().filter(|r| r.name != "*") {
+        largest_cpu_free = largest_cpu_free.max(rr.cpu_total.saturating_sub(rr.cpu_requests));
+        largest_mem_free = largest_mem_free.max(rr.mem_total - rr.mem_requests);
+        largest_storage_free = largest_storage_free.max(rr.storage_total - rr.storage_requests);
+        largest_pods_free = largest_pods_free.max(rr.pods_total.saturating_sub(rr.pods));
+    }
+
     for rr in data {
         let cpu_req_percentage = (rr.cpu_requests as f32 / rr.cpu_total as f32) * 100.0;
         let mem_req_percentage = (rr.mem_requests / rr.mem_total) * 100.0;
-        let cpu_usage_percentage = (rr.cpu_usage as f32 / rr.cpu_total as f32) * 100.0;
-        let mem_usage_percentage = (rr.mem_usage / rr.mem_total) * 100.0;
         let storage_req_percentage = (rr.storage_requests / rr.storage_total) * 100.0;
 
+        let cpu_free = rr.cpu_total.saturating_sub(rr.cpu_requests);
+        let mem_free = (rr.mem_total - rr.mem_requests).max(0.0);
+        let storage_free = (rr.storage_total - rr.storage_requests).max(0.0);
+        let pods_free = rr.pods_total.saturating_sub(rr.pods);
+
+        let (cpu_free, mem_free, storage_free, pods_free) = match rr.name.as_str() {
+            "*" => (
+                format!("{}m (max {}m)", cpu_free, largest_cpu_free),
+                format!("{:.0}Mi (max {:.0}Mi)", mem_free, largest_mem_free),
+                format!("{:.0}Mi (max {:.0}Mi)", storage_free, largest_storage_free),
+                format!("{} (max {})", pods_free, largest_pods_free),
+            ),
+            _ => (
+                format!("{}m", cpu_free),
+                format!("{:.0}Mi", mem_free),
+                format!("{:.0}Mi", storage_free),
+                format!("{}", pods_free),
+            ),
+        };
+
         let rs = kubernetes::ResourceStatus::new(
-            format!("{}", rr.name),
+            rr.name.to_string(),
             format!("{}m ({:.2}%)", rr.cpu_requests, cpu_req_percentage),
-            format!("{}m ({:.2}%)", rr.cpu_usage, cpu_usage_percentage),
+            format_usage(rr.cpu_usage, |u| format!("{}m ({:.2}%)", u, (u as f32 / rr.cpu_total as f32) * 100.0)),
             format!("{}Mi ({:.2}%)", rr.mem_requests, mem_req_percentage),
-            format!("{:.2}Mi ({:.2}%)", rr.mem_usage, mem_usage_percentage),
+            format_usage(rr.mem_usage, |u| format!("{:.2}Mi ({:.2}%)", u, (u / rr.mem_total) * 100.0)),
+            format_efficiency(rr.cpu_usage.map(|u| u as f32), rr.cpu_requests as f32),
+            format_efficiency(rr.mem_usage, rr.mem_requests),
             format!("{}Mi ({:.2}%)", rr.storage_requests, storage_req_percentage),
+            format_usage(rr.storage_usage, |u| format!("{:.0}Mi ({:.2}%)", u, (u / rr.storage_total) * 100.0)),
             format!("{} / {}", rr.pods, rr.pods_total),
+            cpu_free,
+            mem_free,
+            storage_free,
+            pods_free,
         );
         rss.push(rs);
 
 
     }
 
-    return rss;
+    rss
+}
+
+pub fn parse_matrix_data(matrix: BTreeMap<String, BTreeMap<String, (u32, f32)>>, resource: Filter) -> Vec<Vec<String>> {
+    let format_cell = |(cpu, mem): (u32, f32)| match resource {
+        Filter::Mem => format!("{:.0}Mi", mem),
+        _ => format!("{}m", cpu),
+    };
+
+    let columns: BTreeSet<String> = matrix.values().flat_map(|row| row.keys().cloned()).collect();
+
+    let mut records = Vec::new();
+    let mut header = vec![String::from("namespace")];
+    header.extend(columns.iter().cloned());
+    header.push(String::from("*"));
+    records.push(header);
+
+    let mut column_totals: BTreeMap<String, (u32, f32)> = BTreeMap::new();
+    let mut cluster_total: (u32, f32) = (0, 0.0);
+
+    for (namespace, row) in &matrix {
+        let mut record = vec![namespace.clone()];
+        let mut row_total: (u32, f32) = (0, 0.0);
+
+        for column in &columns {
+            let cell = row.get(column).copied().unwrap_or((0, 0.0));
+            record.push(format_cell(cell));
+
+            let column_total = column_totals.entry(column.clone()).or_insert((0, 0.0));
+            column_total.0 += cell.0;
+            column_total.1 += cell.1;
+            row_total.0 += cell.0;
+            row_total.1 += cell.1;
+        }
+
+        cluster_total.0 += row_total.0;
+        cluster_total.1 += row_total.1;
+        record.push(format_cell(row_total));
+        records.push(record);
+    }
+
+    let mut record = vec![String::from("*")];
+    for column in &columns {
+        record.push(format_cell(column_totals[column]));
+    }
+    record.push(format_cell(cluster_total));
+    records.push(record);
+
+    records
+}
+
+pub fn parse_tree_data(tree: BTreeMap<String, kubernetes::NodeTree>) -> Vec<kubernetes::TreeStatus> {
+    let mut rows = Vec::new();
+
+    let tree_row = |name: String, cr: &kubernetes::ContainerResources| kubernetes::TreeStatus::new(
+        name,
+        format!("{}m", cr.cpu_requests),
+        format!("{}m", cr.cpu_limits),
+        format_usage(cr.cpu_usage, |u| format!("{}m", u)),
+        format!("{:.0}Mi", cr.mem_requests),
+        format!("{:.0}Mi", cr.mem_limits),
+        format_usage(cr.mem_usage, |u| format!("{:.2}Mi", u)),
+    );
+
+    for (node_name, node) in tree {
+        let mut node_total = kubernetes::ContainerResources::default();
+        let mut node_rows = Vec::new();
+        let namespaces_len = node.namespaces.len();
+
+        for (i, (namespace, pods)) in node.namespaces.iter().enumerate() {
+            let (ns_branch, ns_indent) = tree_branch(i + 1 == namespaces_len);
+            let mut ns_total = kubernetes::ContainerResources::default();
+            let mut ns_rows = Vec::new();
+
+            for (j, (pod_name, containers)) in pods.iter().enumerate() {
+                let (pod_branch, pod_indent) = tree_branch(j + 1 == pods.len());
+                let mut pod_total = kubernetes::ContainerResources::default();
+                let mut pod_rows = Vec::new();
+
+                for (k, (container_name, cr)) in containers.iter().enumerate() {
+                    let (container_branch, _) = tree_branch(k + 1 == containers.len());
+                    pod_total.add(cr);
+                    pod_rows.push(tree_row(format!("{}{}{}{}", ns_indent, pod_indent, container_branch, container_name), cr));
+                }
+
+                ns_total.add(&pod_total);
+                ns_rows.push(tree_row(format!("{}{}{}", ns_indent, pod_branch, pod_name), &pod_total));
+                ns_rows.append(&mut pod_rows);
+            }
+
+            node_total.add(&ns_total);
+            node_rows.push(tree_row(format!("{}{}", ns_branch, namespace), &ns_total));
+            node_rows.append(&mut ns_rows);
+        }
+
+        let cpu_total = node.cpu_total as f32;
+        rows.push(kubernetes::TreeStatus::new(
+            node_name,
+            format!("{}m ({:.2}%)", node_total.cpu_requests, (node_total.cpu_requests as f32 / cpu_total) * 100.0),
+            format!("{}m ({:.2}%)", node_total.cpu_limits, (node_total.cpu_limits as f32 / cpu_total) * 100.0),
+            format_usage(node_total.cpu_usage, |u| format!("{}m ({:.2}%)", u, (u as f32 / cpu_total) * 100.0)),
+            format!("{:.0}Mi ({:.2}%)", node_total.mem_requests, (node_total.mem_requests / node.mem_total) * 100.0),
+            format!("{:.0}Mi ({:.2}%)", node_total.mem_limits, (node_total.mem_limits / node.mem_total) * 100.0),
+            format_usage(node_total.mem_usage, |u| format!("{:.2}Mi ({:.2}%)", u, (u / node.mem_total) * 100.0)),
+        ));
+        rows.append(&mut node_rows);
+    }
+
+    rows
+}
+
+/// pod volumes with the most used first
+pub fn parse_volume_data(volumes: &[(String, String, f32, Option<f32>)]) -> Vec<kubernetes::VolumeStatus> {
+    let mut volumes = volumes.to_vec();
+    volumes.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
+
+    volumes.into_iter().map(|(pod, volume, used, capacity)| kubernetes::VolumeStatus::new(
+        pod,
+        volume,
+        format!("{:.0}Mi", used),
+        format_usage(capacity, |c| format!("{:.0}Mi ({:.2}%)", c, (used / c) * 100.0)),
+    )).collect()
+}
+
+pub fn parse_node_detail(detail: &kubernetes::NodeDetail) -> Vec<kubernetes::NodeResourceStatus> {
+    let (cpu_capacity, mem_capacity, storage_capacity, pods_capacity) = detail.capacity;
+    let (cpu_total, mem_total, storage_total, pods_total) = detail.allocatable;
+    let (cpu_requests, mem_requests, storage_requests, pods) = detail.requests;
+    let cpu_usage = detail.usage.map(|(cpu, _, _)| cpu);
+    let mem_usage = detail.usage.map(|(_, mem, _)| mem);
+    let storage_usage = detail.usage.and_then(|(_, _, storage)| storage);
+
+    let mut limits = kubernetes::ContainerResources::default();
+    for pod in &detail.pods {
+        limits.add(&pod.resources);
+    }
+
+    let cpu_total_f = cpu_total as f32;
+
+    vec![
+        kubernetes::NodeResourceStatus::new(
+            String::from("cpu"),
+            format!("{}m", cpu_capacity),
+            format!("{}m", cpu_total),
+            format!("{}m ({:.2}%)", cpu_requests, (cpu_requests as f32 / cpu_total_f) * 100.0),
+            format!("{}m ({:.2}%)", limits.cpu_limits, (limits.cpu_limits as f32 / cpu_total_f) * 100.0),
+            format_usage(cpu_usage, |u| format!("{}m ({:.2}%)", u, (u as f32 / cpu_total_f) * 100.0)),
+            format!("{}m", cpu_total.saturating_sub(cpu_requests)),
+        ),
+        kubernetes::NodeResourceStatus::new(
+            String::from("mem"),
+            format!("{:.0}Mi", mem_capacity),
+            format!("{:.0}Mi", mem_total),
+            format!("{:.0}Mi ({:.2}%)", mem_requests, (mem_requests / mem_total) * 100.0),
+            format!("{:.0}Mi ({:.2}%)", limits.mem_limits, (limits.mem_limits / mem_total) * 100.0),
+            format_usage(mem_usage, |u| format!("{:.2}Mi ({:.2}%)", u, (u / mem_total) * 100.0)),
+            format!("{:.0}Mi", (mem_total - mem_requests).max(0.0)),
+        ),
+        kubernetes::NodeResourceStatus::new(
+            String::from("storage"),
+            format!("{:.0}Mi", storage_capacity),
+            format!("{:.0}Mi", storage_total),
+            format!("{:.0}Mi ({:.2}%)", storage_requests, (storage_requests / storage_total) * 100.0),
+            String::from("-"),
+            match storage_usage {
+                Some(u) => format!("{:.0}Mi ({:.2}%)", u, (u / storage_total) * 100.0),
+                None => String::from("-"),
+            },
+            format!("{:.0}Mi", (storage_total - storage_requests).max(0.0)),
+        ),
+        kubernetes::NodeResourceStatus::new(
+            String::from("pods"),
+            format!("{}", pods_capacity),
+            format!("{}", pods_total),
+            format!("{}", pods),
+            String::from("-"),
+            String::from("-"),
+            format!("{}", pods_total.saturating_sub(pods)),
+        ),
+    ]
+}
+
+pub fn sort_pod_resources(pods: &mut [kubernetes::PodResources], sort_by: &Filter, utilization: bool) {
+    match (sort_by, utilization) {
+        (Filter::Mem, false) => pods.sort_by(|a, b| b.resources.mem_requests.partial_cmp(&a.resources.mem_requests).unwrap()),
+        (Filter::Mem, true) => pods.sort_by(|a, b| b.resources.mem_usage.unwrap_or_default().partial_cmp(&a.resources.mem_usage.unwrap_or_default()).unwrap()),
+        (_, false) => pods.sort_by_key(|p| Reverse(p.resources.cpu_requests)),
+        (_, true) => pods.sort_by_key(|p| Reverse(p.resources.cpu_usage.unwrap_or_default())),
+    }
+}
+
+pub fn get_top_consumers(pods: &mut [kubernetes::PodResources], resource: Filter, utilization: bool, n: usize) -> String {
+    sort_pod_resources(pods, &resource, utilization);
+
+    let consumers: Vec<String> = pods.iter().take(n).map(|p| {
+        let amount = match (&resource, utilization) {
+            (Filter::Mem, false) => format!("{:.0}Mi", p.resources.mem_requests),
+            (Filter::Mem, true) => format_usage(p.resources.mem_usage, |u| format!("{:.2}Mi", u)),
+            (_, false) => format!("{}m", p.resources.cpu_requests),
+            (_, true) => format_usage(p.resources.cpu_usage, |u| format!("{}m", u)),
+        };
+        format!("{}/{} ({})", p.namespace, p.name, amount)
+    }).collect();
+
+    match consumers.is_empty() {
+        true => String::from("<none>"),
+        false => consumers.join(", "),
+    }
+}
+
+/// min, avg, p95 and max of the samples, p95 by nearest rank
+pub fn get_usage_stats(samples: &[f32]) -> Option<[f32; 4]> {
+    if samples.is_empty() {
+        return None;
+    }
+
+    let mut sorted = samples.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let avg = sorted.iter().sum::<f32>() / sorted.len() as f32;
+    let rank = ((sorted.len() as f32 * 0.95).ceil() as usize).max(1);
+
+    Some([sorted[0], avg, sorted[rank - 1], sorted[sorted.len() - 1]])
+}
+
+pub fn parse_usage_samples(rows: &[kubernetes::UsageSamples]) -> Vec<kubernetes::UsageSampleStatus> {
+    rows.iter().map(|row| {
+        let cpu: Vec<f32> = row.cpu.iter().map(|c| *c as f32).collect();
+        let cpu = match get_usage_stats(&cpu) {
+            Some(stats) => stats.map(|c| format!("{:.0}m", c)),
+            None => std::array::from_fn(|_| String::from("unknown")),
+        };
+        let mem = match get_usage_stats(&row.mem) {
+            Some(stats) => stats.map(|m| format!("{:.0}Mi", m)),
+            None => std::array::from_fn(|_| String::from("unknown")),
+        };
+
+        kubernetes::UsageSampleStatus::new(row.name.clone(), format!("{}", row.cpu.len()), cpu, mem)
+    }).collect()
+}
+
+/// cpu requests and limits and mem requests and limits sized from the p95 and peak usage plus headroom,
+/// limits need the peak over a prometheus window or at least two samples, a single reading has no headroom over p95
+pub fn get_recommendation(cu: &kubernetes::ContainerUsage, headroom: f32) -> Option<(u32, Option<u32>, f32, Option<f32>)> {
+    let [_, _, cpu_p95, cpu_max] = get_usage_stats(&cpu_samples(cu))?;
+    let [_, _, mem_p95, mem_max] = get_usage_stats(&cu.mem)?;
+    let factor = 1.0 + headroom / 100.0;
+
+    let cpu_round = |c: f32| ((c * factor / 10.0).ceil() as u32 * 10).max(10);
+    let mem_round = |m: f32| (m * factor).ceil().max(16.0);
+
+    let cpu_requests = cpu_round(cpu_p95);
+    let mem_requests = mem_round(mem_p95);
+
+    let peak = match (cu.cpu_peak, cu.mem_peak) {
+        (Some(cpu), Some(mem)) => Some((cpu_max.max(cpu as f32), mem_max.max(mem))),
+        _ if cu.cpu.len() > 1 && cu.mem.len() > 1 => Some((cpu_max, mem_max)),
+        _ => None,
+    };
+
+    Some((
+        cpu_requests,
+        peak.map(|(cpu, _)| cpu_round(cpu).max(cpu_requests)),
+        mem_requests,
+        peak.map(|(_, mem)| mem_round(mem).max(mem_requests)),
+    ))
+}
+
+fn cpu_samples(cu: &kubernetes::ContainerUsage) -> Vec<f32> {
+    cu.cpu.iter().map(|c| *c as f32).collect()
+}
+
+/// rows of the rightsizing report and the cpu and mem all recommendations would reclaim
+pub fn parse_rightsize_data(containers: &[kubernetes::ContainerUsage], headroom: f32, sort_by: Filter) -> (Vec<kubernetes::RightsizeStatus>, (i64, f32)) {
+    // init containers have finished by the time usage is read
+    let mut rows: Vec<_> = containers.iter().filter(|cu| !cu.init).map(|cu| {
+        let recommendation = get_recommendation(cu, headroom);
+        let reclaimed = recommendation.map(|(cpu, _, mem, _)| (
+            (cu.resources.cpu_requests as i64 - cpu as i64) * cu.replicas as i64,
+            (cu.resources.mem_requests - mem) * cu.replicas as f32,
+        ));
+        (cu, recommendation, reclaimed)
+    }).collect();
+
+    match sort_by {
+        Filter::Mem => rows.sort_by(|a, b| b.2.map(|r| r.1).unwrap_or(f32::MIN).partial_cmp(&a.2.map(|r| r.1).unwrap_or(f32::MIN)).unwrap()),
+        _ => rows.sort_by_key(|r| Reverse(r.2.map(|r| r.0).unwrap_or(i64::MIN))),
+    }
+
+    let mut total = (0, 0.0);
+    let format_cpu = |c: u32| match c {
+        0 => String::from("-"),
+        _ => format!("{}m", c),
+    };
+    let format_mem = |m: f32| match m > 0.0 {
+        true => format!("{:.0}Mi", m),
+        false => String::from("-"),
+    };
+
+    let rows = rows.into_iter().map(|(cu, recommendation, reclaimed)| {
+        let current = &cu.resources;
+        let change = |from: String, to: Option<String>| format!("{} -> {}", from, to.unwrap_or_else(|| String::from("unknown")));
+
+        if let Some((cpu, mem)) = reclaimed {
+            total.0 += cpu;
+            total.1 += mem;
+        }
+
+        kubernetes::RightsizeStatus::new(
+            format!("{}/{}", cu.namespace, cu.workload),
+            cu.container.clone(),
+            format!("{}", cu.replicas),
+            change(format_cpu(current.cpu_requests), recommendation.map(|r| format_cpu(r.0))),
+            change(format_cpu(current.cpu_limits), recommendation.and_then(|r| r.1).map(format_cpu)),
+            change(format_mem(current.mem_requests), recommendation.map(|r| format_mem(r.2))),
+            change(format_mem(current.mem_limits), recommendation.and_then(|r| r.3).map(format_mem)),
+            format_efficiency(get_usage_stats(&cpu_samples(cu)).map(|[_, avg, _, _]| avg), current.cpu_requests as f32),
+            format_efficiency(get_usage_stats(&cu.mem).map(|[_, avg, _, _]| avg), current.mem_requests),
+            format_usage(reclaimed, |(cpu, _)| format!("{}m", cpu)),
+            format_usage(reclaimed, |(_, mem)| format!("{:.0}Mi", mem)),
+        )
+    }).collect();
+
+    (rows, total)
+}
+
+/// namespaces holding requests with a max cpu usage below the threshold or without pod activity for `days`,
+/// and the cpu and mem they reserve
+pub fn parse_idle_data(namespaces: &[kubernetes::NamespaceActivity], cpu_threshold: u32, days: i64, now: DateTime<Utc>) -> (Vec<kubernetes::IdleNamespaceStatus>, (u32, f32)) {
+    let mut rows = Vec::new();
+    let mut reserved = (0, 0.0);
+
+    for namespace in namespaces {
+        let (cpu, mem, _, pods) = namespace.requests;
+        if cpu == 0 && mem == 0.0 {
+            continue;
+        }
+
+        let max_cpu = namespace.cpu.iter().max().copied();
+        let inactive_days = namespace.last_activity.map(|t| (now - t).num_days());
+
+        let mut reasons = Vec::new();
+        if let Some(max_cpu) = max_cpu.filter(|c| *c < cpu_threshold) {
+            reasons.push(format!("cpu usage {}m below {}m", max_cpu, cpu_threshold));
+        }
+        if let Some(inactive_days) = inactive_days.filter(|d| *d >= days) {
+            reasons.push(format!("no new pods or restarts for {}d", inactive_days));
+        }
+
+        if reasons.is_empty() {
+            continue;
+        }
+
+        reserved.0 += cpu;
+        reserved.1 += mem;
+
+        rows.push(kubernetes::IdleNamespaceStatus::new(
+            namespace.name.clone(),
+            format!("{}", pods),
+            format!("{}m", cpu),
+            format!("{:.0}Mi", mem),
+            format_usage(max_cpu, |c| format!("{}m", c)),
+            format_usage(inactive_days, |d| format!("{}d ago", d)),
+            reasons.join(", "),
+        ));
+    }
+
+    (rows, reserved)
+}
+
+pub fn parse_stuck_data(pods: &[kubernetes::StuckPod]) -> Vec<kubernetes::StuckPodStatus> {
+    pods.iter().map(|p| kubernetes::StuckPodStatus::new(
+        p.namespace.clone(),
+        p.name.clone(),
+        p.node.clone(),
+        format!("{}m", p.cpu_requests),
+        format!("{:.0}Mi", p.mem_requests),
+        format!("{}", p.restarts),
+        p.reasons.join(", "),
+    )).collect()
+}
+
+/// requests held by stuck pods per namespace, with the cluster total as the last row
+pub fn parse_stuck_namespace_data(pods: &[kubernetes::StuckPod]) -> Vec<kubernetes::StuckNamespaceStatus> {
+    let mut namespaces: BTreeMap<&str, (usize, u32, f32, i32)> = BTreeMap::new();
+    let mut total = (0, 0, 0.0, 0);
+
+    for p in pods {
+        let entry = namespaces.entry(p.namespace.as_str()).or_default();
+        for sum in [entry, &mut total] {
+            sum.0 += 1;
+            sum.1 += p.cpu_requests;
+            sum.2 += p.mem_requests;
+            sum.3 += p.restarts;
+        }
+    }
+
+    namespaces.into_iter()
+        .chain(std::iter::once(("*", total)))
+        .map(|(namespace, (pods, cpu, mem, restarts))| kubernetes::StuckNamespaceStatus::new(
+            namespace.to_string(),
+            format!("{}", pods),
+            format!("{}m", cpu),
+            format!("{:.0}Mi", mem),
+            format!("{}", restarts),
+        ))
+        .collect()
+}
+
+/// containers missing requests or limits, by namespace and workload, with their avg usage per replica,
+/// and the number of workloads they belong to
+pub fn parse_audit_data(containers: &[kubernetes::ContainerUsage], requests_only: bool) -> (Vec<kubernetes::AuditStatus>, usize) {
+    let mut workloads = BTreeSet::new();
+
+    let rows = containers.iter()
+        .filter_map(|cu| {
+            let missing: Vec<&str> = cu.missing.iter()
+                .filter(|m| !requests_only || m.ends_with("requests"))
+                .copied()
+                .collect();
+
+            if missing.is_empty() {
+                return None;
+            }
+            workloads.insert((&cu.namespace, &cu.workload));
+
+            Some(kubernetes::AuditStatus::new(
+                format!("{}/{}", cu.namespace, cu.workload),
+                match cu.init {
+                    true => format!("{} (init)", cu.container),
+                    false => cu.container.clone(),
+                },
+                format!("{}", cu.replicas),
+                cu.qos.clone(),
+                missing.join(", "),
+                format_usage(get_usage_stats(&cpu_samples(cu)), |[_, avg, _, _]| format!("{:.0}m", avg)),
+                format_usage(get_usage_stats(&cu.mem), |[_, avg, _, _]| format!("{:.0}Mi", avg)),
+            ))
+        })
+        .collect();
+
+    (rows, workloads.len())
+}
+
+/// containers whose working set is at least `threshold` percent of their memory limit, closest to the limit first
+pub fn parse_oom_risk_data(containers: &[kubernetes::ContainerMemory], threshold: f32) -> Vec<kubernetes::OomRiskStatus> {
+    let mut at_risk: Vec<(&kubernetes::ContainerMemory, f32)> = containers.iter()
+        .map(|c| (c, (c.usage / c.limit) * 100.0))
+        .filter(|(_, percentage)| *percentage >= threshold)
+        .collect();
+    at_risk.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    at_risk.into_iter().map(|(c, percentage)| kubernetes::OomRiskStatus::new(
+        c.namespace.clone(),
+        c.pod.clone(),
+        c.container.clone(),
+        c.node.clone(),
+        format!("{:.0}Mi", c.limit),
+        format!("{:.2}Mi ({:.2}%)", c.usage, percentage),
+    )).collect()
+}
+
+/// nodes whose memory usage is at least `threshold` percent of allocatable
+pub fn parse_node_memory_data(rrs: &[kubernetes::ResouceRequests], threshold: f32) -> Vec<kubernetes::NodeMemoryStatus> {
+    rrs.iter()
+        .filter(|r| r.name != "*")
+        .filter_map(|r| r.mem_usage.map(|u| (r, u, (u / r.mem_total) * 100.0)))
+        .filter(|(_, _, percentage)| *percentage >= threshold)
+        .map(|(r, usage, percentage)| kubernetes::NodeMemoryStatus::new(
+            r.name.clone(),
+            format!("{:.0}Mi", r.mem_total),
+            format!("{:.0}Mi ({:.2}%)", r.mem_requests, (r.mem_requests / r.mem_total) * 100.0),
+            format!("{:.2}Mi ({:.2}%)", usage, percentage),
+        ))
+        .collect()
+}
+
+pub fn parse_node_pressure_data(nodes: &[kubernetes::NodePressure]) -> Vec<kubernetes::NodePressureStatus> {
+    nodes.iter().map(|n| {
+        let condition = |name: &str| n.conditions.iter()
+            .find(|(t, _)| t == name)
+            .map(|(_, status)| status.clone())
+            .unwrap_or_else(|| String::from("Unknown"));
+        let count = |reason: &str| n.events.iter().filter(|e| e.reason == reason).count();
+
+        kubernetes::NodePressureStatus::new(
+            n.name.clone(),
+            condition("MemoryPressure"),
+            condition("DiskPressure"),
+            condition("PIDPressure"),
+            format!("{:.0}Mi ({:.2}%)", n.mem_requests, (n.mem_requests / n.mem_allocatable) * 100.0),
+            format_usage(n.mem_usage, |u| format!("{:.2}Mi ({:.2}%)", u, (u / n.mem_allocatable) * 100.0)),
+            format!("{}", count("OOMKilled")),
+            format!("{}", count("Evicted")),
+        )
+    }).collect()
+}
+
+pub fn parse_pressure_event_data(nodes: &[kubernetes::NodePressure]) -> Vec<kubernetes::PressureEventStatus> {
+    nodes.iter().flat_map(|n| n.events.iter().map(|e| kubernetes::PressureEventStatus::new(
+        n.name.clone(),
+        format!("{}/{}", e.namespace, e.pod),
+        e.container.clone().unwrap_or_else(|| String::from("-")),
+        e.reason.clone(),
+        format_usage(e.time, |t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+        e.message.clone(),
+    ))).collect()
+}
+
+/// durations like 500ms, 10s, 5m, 1h or 7d
+pub fn parse_duration(duration: &str) -> Result<std::time::Duration, String> {
+    let split = duration.find(|c: char| !c.is_ascii_digit()).unwrap_or(duration.len());
+    let (n, unit) = duration.split_at(split);
+    let n = n.parse::<u64>().map_err(|_| format!("invalid duration {}", duration))?;
+
+    match unit {
+        "ms" => Ok(std::time::Duration::from_millis(n)),
+        "s" => Ok(std::time::Duration::from_secs(n)),
+        "m" => Ok(std::time::Duration::from_secs(n * 60)),
+        "h" => Ok(std::time::Duration::from_secs(n * 3600)),
+        "d" => Ok(std::time::Duration::from_secs(n * 86400)),
+        _ => Err(format!("invalid duration {}", duration)),
+    }
+}
+
+/// usage as a percentage of requests, none when either is unknown or nothing is requested
+pub fn get_efficiency(usage: Option<f32>, requests: f32) -> Option<f32> {
+    match requests > 0.0 {
+        true => usage.map(|u| (u / requests) * 100.0),
+        false => None,
+    }
 }
 
+pub fn format_efficiency(usage: Option<f32>, requests: f32) -> String {
+    match (usage, get_efficiency(usage, requests)) {
+        (None, _) => String::from("unknown"),
+        (_, None) => String::from("-"),
+        (_, Some(e)) => format!("{:.2}%", e),
+    }
+}
+
+pub fn format_usage<T>(usage: Option<T>, format: impl Fn(T) -> String) -> String {
+    match usage {
+        Some(u) => format(u),
+        None => String::from("unknown"),
+    }
+}
+
+pub fn get_fit_count(free_cpu: u32, free_mem: f32, free_pods: usize, cpu: u32, mem: f32) -> usize {
+    let mut count = free_pods;
+
+    if let Some(n) = free_cpu.checked_div(cpu) {
+        count = count.min(n as usize);
+    }
+    if mem > 0.0 {
+        count = count.min((free_mem / mem).max(0.0) as usize);
+    }
+
+    count
+}
+
+pub fn parse_fit_data(nodes: &[scheduler::NodeState], pod: &scheduler::PodShape) -> Vec<kubernetes::FitStatus> {
+    let mut rows = Vec::new();
+
+    for node in nodes {
+        let (cpu_free, mem_free, _, pods_free) = node.free();
+        let (fits, reason) = match node.check(pod) {
+            Ok(count) => (format!("{}", count), String::from("-")),
+            Err(reason) => (String::from("0"), reason),
+        };
+
+        rows.push(kubernetes::FitStatus::new(
+            node.name.clone(),
+            format!("{}m", cpu_free),
+            format!("{:.0}Mi", mem_free),
+            format!("{}", pods_free),
+            fits,
+            reason,
+        ));
+    }
+
+    rows
+}
+
+pub fn parse_fragmentation_data(nodes: &[scheduler::NodeState], node_group: &Option<String>, sort_by: Filter) -> Vec<kubernetes::FragmentationStatus> {
+    let mut groups: BTreeMap<String, Vec<&scheduler::NodeState>> = BTreeMap::new();
+
+    if let Some(label) = node_group {
+        for node in nodes {
+            let group = node.labels.get(label).cloned().unwrap_or_else(|| String::from("<none>"));
+            groups.entry(group).or_default().push(node);
+        }
+    }
+
+    let by_memory = matches!(sort_by, Filter::Mem);
+    let mut data: Vec<(String, scheduler::Fragmentation)> = groups.iter()
+        .map(|(group, nodes)| (group.clone(), scheduler::get_fragmentation(nodes, by_memory)))
+        .collect();
+
+    match sort_by {
+        Filter::Cpu => data.sort_by_key(|(_, f)| Reverse(f.cpu_largest)),
+        Filter::Mem => data.sort_by(|(_, a), (_, b)| b.mem_largest.partial_cmp(&a.mem_largest).unwrap()),
+        Filter::Fragmentation => data.sort_by(|(_, a), (_, b)| b.index.partial_cmp(&a.index).unwrap()),
+        _ => (),
+    }
+
+    let all_nodes: Vec<&scheduler::NodeState> = nodes.iter().collect();
+    data.push((String::from("*"), scheduler::get_fragmentation(&all_nodes, by_memory)));
+
+    data.into_iter().map(|(group, f)| kubernetes::FragmentationStatus::new(
+        group,
+        format!("{}", f.nodes),
+        format!("{}m", f.cpu_free),
+        format!("{}m", f.cpu_largest),
+        format!("{:.0}Mi", f.mem_free),
+        format!("{:.0}Mi", f.mem_largest),
+        format!("{:.2}%", f.index * 100.0),
+    )).collect()
+}
+
+pub fn parse_placement_data(placements: &[(&scheduler::ScheduledPod, Result<String, String>)]) -> Vec<kubernetes::PlacementStatus> {
+    placements.iter().map(|(pod, to)| {
+        let (to, reason) = match to {
+            Ok(node) => (node.clone(), String::from("-")),
+            Err(reason) => (String::from("Pending"), reason.clone()),
+        };
+
+        kubernetes::PlacementStatus::new(
+            pod.shape.name.clone(),
+            pod.node.clone(),
+            to,
+            format!("{}m", pod.shape.cpu_requests),
+            format!("{:.0}Mi", pod.shape.mem_requests),
+            reason,
+        )
+    }).collect()
+}
+
+pub fn parse_nodes_needed_data(needed: scheduler::NodesNeeded) -> Vec<kubernetes::InstanceTypeStatus> {
+    needed.into_iter().map(|(instance_type, needed)| match needed {
+        Ok((per_node, count)) => kubernetes::InstanceTypeStatus::new(instance_type, format!("{}", per_node), format!("{}", count)),
+        Err(reason) => kubernetes::InstanceTypeStatus::new(instance_type, String::from("0"), reason),
+    }).collect()
+}
+
+pub fn parse_workload_data(requests: &[(String, usize, scheduler::PodShape)]) -> Vec<kubernetes::WorkloadStatus> {
+    requests.iter().map(|(name, pods, shape)| kubernetes::WorkloadStatus::new(
+        name.clone(),
+        format!("{}", pods),
+        format!("{}m", shape.cpu_requests * *pods as u32),
+        format!("{:.0}Mi", shape.mem_requests * *pods as f32),
+        format!("{:.0}Mi", shape.storage_requests * *pods as f32),
+    )).collect()
+}
+
+pub fn parse_impact_data(nodes: &[scheduler::NodeState], requests: &[(String, usize, scheduler::PodShape)]) -> Vec<kubernetes::ImpactStatus> {
+    let (mut cpu_total, mut mem_total, mut storage_total, mut pods_total) = (0, 0.0, 0.0, 0);
+    let (mut cpu_before, mut mem_before, mut storage_before, mut pods_before) = (0, 0.0, 0.0, 0);
+    let (mut cpu_added, mut mem_added, mut storage_added, mut pods_added) = (0, 0.0, 0.0, 0);
+
+    for node in nodes {
+        cpu_total += node.allocatable.0;
+        mem_total += node.allocatable.1;
+        storage_total += node.allocatable.2;
+        pods_total += node.allocatable.3;
+        cpu_before += node.requests.0;
+        mem_before += node.requests.1;
+        storage_before += node.requests.2;
+        pods_before += node.requests.3;
+    }
+
+    for (_, pods, shape) in requests {
+        cpu_added += shape.cpu_requests * *pods as u32;
+        mem_added += shape.mem_requests * *pods as f32;
+        storage_added += shape.storage_requests * *pods as f32;
+        pods_added += pods;
+    }
+
+    let cpu_total_f = cpu_total as f32;
+    let pods_total_f = pods_total as f32;
+
+    vec![
+        kubernetes::ImpactStatus::new(
+            String::from("cpu"),
+            format!("{}m", cpu_total),
+            format!("{}m ({:.2}%)", cpu_before, (cpu_before as f32 / cpu_total_f) * 100.0),
+            format!("{}m", cpu_added),
+            format!("{}m ({:.2}%)", cpu_before + cpu_added, ((cpu_before + cpu_added) as f32 / cpu_total_f) * 100.0),
+        ),
+        kubernetes::ImpactStatus::new(
+            String::from("mem"),
+            format!("{:.0}Mi", mem_total),
+            format!("{:.0}Mi ({:.2}%)", mem_before, (mem_before / mem_total) * 100.0),
+            format!("{:.0}Mi", mem_added),
+            format!("{:.0}Mi ({:.2}%)", mem_before + mem_added, ((mem_before + mem_added) / mem_total) * 100.0),
+        ),
+        kubernetes::ImpactStatus::new(
+            String::from("storage"),
+            format!("{:.0}Mi", storage_total),
+            format!("{:.0}Mi ({:.2}%)", storage_before, (storage_before / storage_total) * 100.0),
+            format!("{:.0}Mi", storage_added),
+            format!("{:.0}Mi ({:.2}%)", storage_before + storage_added, ((storage_before + storage_added) / storage_total) * 100.0),
+        ),
+        kubernetes::ImpactStatus::new(
+            String::from("pods"),
+            format!("{}", pods_total),
+            format!("{} ({:.2}%)", pods_before, (pods_before as f32 / pods_total_f) * 100.0),
+            format!("{}", pods_added),
+            format!("{} ({:.2}%)", pods_before + pods_added, ((pods_before + pods_added) as f32 / pods_total_f) * 100.0),
+        ),
+    ]
+}
+
+fn tree_branch(last: bool) -> (&'static str, &'static str) {
+    match last {
+        true => ("└─ ", "   "),
+        false => ("├─ ", "│  "),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 pub async fn add_data(
-    node_name: String, cpu_requests: u32, cpu_total: u32, cpu_usage: u32, mem_requests: f32,
-    mem_total: f32, mem_usage: f32, storage_requests: f32, storage_total: f32, pods: usize,
-    pods_total: usize, rrs: &mut Vec<kubernetes::ResouceRequests>
+    node_name: String, cpu_requests: u32, cpu_total: u32, cpu_usage: Option<u32>, mem_requests: f32,
+    mem_total: f32, mem_usage: Option<f32>, storage_requests: f32, storage_total: f32, storage_usage: Option<f32>,
+    pods: usize, pods_total: usize, rrs: &mut Vec<kubernetes::ResouceRequests>
 ) {
     rrs.push(kubernetes::ResouceRequests::new(
         node_name,
@@ -85,44 +859,50 @@ pub async fn add_data(
         mem_usage,
         storage_requests,
         storage_total,
+        storage_usage,
         pods,
         pods_total,
     ));
 }
 
-pub fn parse_cpu_requests(cpu: String) -> u32 {
-    if cpu.contains(".") {
-        let n = cpu.replace(".", "");
-        return n.parse::<u32>().unwrap() * 100;
-    } else if let Some((n, _unit)) = cpu.split_once("m") {
-        return n.parse::<u32>().unwrap();
-    } else if let Some((n, _unit)) = cpu.split_once("n") {
-        return (n.parse::<f32>().unwrap() / 1000000.0) as u32;
-    } else {
-        return cpu.parse::<u32>().unwrap() * 1000;
-    }
-}
-
-pub fn parse_capacity_requests(mem: String) -> f32 {
-    if let Some((n, _unit)) = mem.split_once("Ki") {
-        return n.parse::<f32>().unwrap() / 1024.0;
-    } else if let Some((n, _unit)) = mem.split_once("Mi") {
-        return n.parse::<f32>().unwrap();
-    } else if let Some((n, _unit)) = mem.split_once("Gi") {
-        return n.parse::<f32>().unwrap() * 1024.0;
-    } else if let Some((n, _unit)) = mem.split_once("Ti") {
-        return n.parse::<f32>().unwrap() * 1024.0 * 1024.0;
-    } else if let Some((n, _unit)) = mem.split_once("m") {
-        return n.parse::<f32>().unwrap() / 1024.0 / 1024.0 / 1024.0;
-    } else if let Some((n, _unit)) = mem.split_once("k") {
-        return n.parse::<f32>().unwrap() / 1000.0 * 0.953674;
-    } else if let Some((n, _unit)) = mem.split_once("M") {
-        return n.parse::<f32>().unwrap() * 0.953674;
-    } else if let Some((n, _unit)) = mem.split_once("G") {
-        return n.parse::<f32>().unwrap() * 0.953674 * 1000.0;
-    } else if let Some((n, _unit)) = mem.split_once("T") {
-        return n.parse::<f32>().unwrap() * 0.953674 * 1000.0 * 1000.0;
-    } else {
-        return mem.parse::<f32>().unwrap() / 1024.0 / 1024.0;
-    }
-}
\ No newline at end of file
+/// a quantity in its base unit, cores or bytes, eg: 0.25, 100m, 1.5Gi, 129e6 or 128974848
+fn parse_quantity(quantity: &str) -> Result<f64, Error> {
+    let invalid = || Error::Parse(format!("invalid quantity {}", quantity));
+
+    let split = quantity.find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '+' | '-'))).unwrap_or(quantity.len());
+    let (number, suffix) = quantity.split_at(split);
+    let number = number.parse::<f64>().map_err(|_| invalid())?;
+
+    let multiplier = match suffix {
+        "" => 1.0,
+        "n" => 1e-9,
+        "u" => 1e-6,
+        "m" => 1e-3,
+        "k" => 1e3,
+        "M" => 1e6,
+        "G" => 1e9,
+        "T" => 1e12,
+        "P" => 1e15,
+        "E" => 1e18,
+        "Ki" => 1024f64,
+        "Mi" => 1024f64.powi(2),
+        "Gi" => 1024f64.powi(3),
+        "Ti" => 1024f64.powi(4),
+        "Pi" => 1024f64.powi(5),
+        "Ei" => 1024f64.powi(6),
+        _ if suffix.starts_with(['e', 'E']) => 10f64.powi(suffix[1..].parse::<i32>().map_err(|_| invalid())?),
+        _ => return Err(invalid()),
+    };
+
+    Ok(number * multiplier)
+}
+
+/// millicores of a cpu quantity
+pub fn parse_cpu_requests(cpu: String) -> Result<u32, Error> {
+    Ok((parse_quantity(&cpu)? * 1000.0).round() as u32)
+}
+
+/// mebibytes of a memory or storage quantity
+pub fn parse_capacity_requests(mem: String) -> Result<f32, Error> {
+    Ok((parse_quantity(&mem)? / 1024f64.powi(2)) as f32)
+}
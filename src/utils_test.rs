@@ -1,30 +1,406 @@
+use std::collections::BTreeMap;
+
 use super::utils::*;
+use super::{error::Error, kubernetes};
 
 #[test]
 fn test_parse_cpu_requests() {
-    assert_eq!(parse_cpu_requests(String::from("100m")), 100);
-    assert_eq!(parse_cpu_requests(String::from("500m")), 500);
-    assert_eq!(parse_cpu_requests(String::from("1")), 1000);
-    assert_eq!(parse_cpu_requests(String::from("2")), 2000);
-    assert_eq!(parse_cpu_requests(String::from("2.5")), 2500);
-    assert_eq!(parse_cpu_requests(String::from("12.5")), 12500);
+    assert_eq!(parse_cpu_requests(String::from("100m")), Ok(100));
+    assert_eq!(parse_cpu_requests(String::from("500m")), Ok(500));
+    assert_eq!(parse_cpu_requests(String::from("1")), Ok(1000));
+    assert_eq!(parse_cpu_requests(String::from("2")), Ok(2000));
+    assert_eq!(parse_cpu_requests(String::from("2.5")), Ok(2500));
+    assert_eq!(parse_cpu_requests(String::from("12.5")), Ok(12500));
+    assert_eq!(parse_cpu_requests(String::from("0.25")), Ok(250));
+    assert_eq!(parse_cpu_requests(String::from("1.25")), Ok(1250));
+    assert_eq!(parse_cpu_requests(String::from(".5")), Ok(500));
+    assert_eq!(parse_cpu_requests(String::from("1e3")), Ok(1000000));
+    assert_eq!(parse_cpu_requests(String::from("250000000n")), Ok(250));
+    assert_eq!(parse_cpu_requests(String::from("1500u")), Ok(2));
+    assert!(matches!(parse_cpu_requests(String::from("2x")), Err(Error::Parse(_))));
+    assert!(matches!(parse_cpu_requests(String::from("")), Err(Error::Parse(_))));
+    assert!(matches!(parse_cpu_requests(String::from("1.2.3")), Err(Error::Parse(_))));
 }
 
 #[test]
 fn test_parse_capacity_requests() {
-    assert_eq!(parse_capacity_requests(String::from("1000Ki")), 0.9765625);
-    assert_eq!(parse_capacity_requests(String::from("1Mi")), 1.0);
-    assert_eq!(parse_capacity_requests(String::from("1000Mi")), 1000.0);
-    assert_eq!(parse_capacity_requests(String::from("1Gi")), 1024.0);
-    assert_eq!(parse_capacity_requests(String::from("10Gi")), 10240.0);
-    assert_eq!(parse_capacity_requests(String::from("3Ti")), 3145728.0);
-    assert_eq!(parse_capacity_requests(String::from("1.5Gi")), 1536.0);
-    assert_eq!(parse_capacity_requests(String::from("1.3Ti")), 1363148.8);
-    assert_eq!(parse_capacity_requests(String::from("10.5Mi")), 10.5);
-    assert_eq!(parse_capacity_requests(String::from("53M")), 50.544724);
-    assert_eq!(parse_capacity_requests(String::from("20k")), 0.01907348);
-    assert_eq!(parse_capacity_requests(String::from("10G")), 9536.74);
-    assert_eq!(parse_capacity_requests(String::from("1M")), 0.953674);
-    assert_eq!(parse_capacity_requests(String::from("1T")), 953674.0);
-
-}
\ No newline at end of file
+    assert_eq!(parse_capacity_requests(String::from("1000Ki")), Ok(0.9765625));
+    assert_eq!(parse_capacity_requests(String::from("1Mi")), Ok(1.0));
+    assert_eq!(parse_capacity_requests(String::from("1000Mi")), Ok(1000.0));
+    assert_eq!(parse_capacity_requests(String::from("1Gi")), Ok(1024.0));
+    assert_eq!(parse_capacity_requests(String::from("10Gi")), Ok(10240.0));
+    assert_eq!(parse_capacity_requests(String::from("3Ti")), Ok(3145728.0));
+    assert_eq!(parse_capacity_requests(String::from("1.5Gi")), Ok(1536.0));
+    assert_eq!(parse_capacity_requests(String::from("1.3Ti")), Ok(1363148.8));
+    assert_eq!(parse_capacity_requests(String::from("10.5Mi")), Ok(10.5));
+    assert_eq!(parse_capacity_requests(String::from("53M")), Ok(50.54474));
+    assert_eq!(parse_capacity_requests(String::from("20k")), Ok(0.019073486));
+    assert_eq!(parse_capacity_requests(String::from("10G")), Ok(9536.743));
+    assert_eq!(parse_capacity_requests(String::from("1M")), Ok(0.9536743));
+    assert_eq!(parse_capacity_requests(String::from("1T")), Ok(953674.3));
+    assert_eq!(parse_capacity_requests(String::from("100e6")), Ok(95.36743));
+    assert_eq!(parse_capacity_requests(String::from("129e6")), Ok(123.02399));
+    assert_eq!(parse_capacity_requests(String::from("128974848")), Ok(123.0));
+    assert_eq!(parse_capacity_requests(String::from("1E")), Ok(953674300000.0));
+    assert!(matches!(parse_capacity_requests(String::from("1Gb")), Err(Error::Parse(_))));
+    assert!(matches!(parse_capacity_requests(String::from("Mi")), Err(Error::Parse(_))));
+
+}
+
+#[test]
+fn test_parse_matrix_data() {
+    let mut matrix = BTreeMap::new();
+    matrix.insert(String::from("default"), BTreeMap::from([
+        (String::from("worker"), (500, 256.0)),
+    ]));
+    matrix.insert(String::from("kube-system"), BTreeMap::from([
+        (String::from("worker"), (100, 70.0)),
+        (String::from("worker2"), (250, 128.0)),
+    ]));
+
+    let cpu = parse_matrix_data(matrix.clone(), Filter::Cpu);
+    assert_eq!(cpu[0], vec!["namespace", "worker", "worker2", "*"]);
+    assert_eq!(cpu[1], vec!["default", "500m", "0m", "500m"]);
+    assert_eq!(cpu[2], vec!["kube-system", "100m", "250m", "350m"]);
+    assert_eq!(cpu[3], vec!["*", "600m", "250m", "850m"]);
+
+    let mem = parse_matrix_data(matrix, Filter::Mem);
+    assert_eq!(mem[3], vec!["*", "326Mi", "128Mi", "454Mi"]);
+}
+
+#[test]
+fn test_parse_tree_data() {
+    let container = kubernetes::ContainerResources {
+        cpu_requests: 100,
+        cpu_limits: 200,
+        mem_requests: 128.0,
+        mem_limits: 256.0,
+        ..Default::default()
+    };
+
+    let mut pods = BTreeMap::new();
+    pods.insert(String::from("nginx-0"), vec![
+        (String::from("nginx"), container.clone()),
+        (String::from("sidecar"), container),
+    ]);
+
+    let mut tree = BTreeMap::new();
+    tree.insert(String::from("worker"), kubernetes::NodeTree {
+        cpu_total: 1000,
+        mem_total: 1024.0,
+        namespaces: BTreeMap::from([(String::from("default"), pods)]),
+    });
+
+    let mut table = tabled::Table::new(parse_tree_data(tree));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 6);
+    assert!(lines[1].contains("worker") && lines[1].contains("200m (20.00%)") && lines[1].contains("512Mi (50.00%)"));
+    assert!(lines[2].contains("└─ default") && lines[2].contains("200m"));
+    assert!(lines[3].contains("   └─ nginx-0") && lines[3].contains("200m"));
+    assert!(lines[4].contains("      ├─ nginx") && lines[4].contains("100m"));
+    assert!(lines[5].contains("      └─ sidecar") && lines[5].contains("128Mi"));
+}
+
+#[test]
+fn test_get_fit_count() {
+    assert_eq!(get_fit_count(4000, 8192.0, 100, 1000, 1024.0), 4);
+    assert_eq!(get_fit_count(4000, 2048.0, 100, 1000, 1024.0), 2);
+    assert_eq!(get_fit_count(4000, 8192.0, 3, 1000, 1024.0), 3);
+    assert_eq!(get_fit_count(500, 8192.0, 100, 1000, 0.0), 0);
+    assert_eq!(get_fit_count(500, -10.0, 100, 0, 1024.0), 0);
+    assert_eq!(get_fit_count(500, 8192.0, 7, 0, 0.0), 7);
+}
+
+#[test]
+fn test_parse_resource_data_free() {
+    let rrs = vec![
+        kubernetes::ResouceRequests::new(String::from("worker"), 3000, 4000, None, 1024.0, 4096.0, None, 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("worker2"), 500, 4000, None, 3072.0, 4096.0, None, 0.0, 1000.0, None, 5, 110),
+        kubernetes::ResouceRequests::new(String::from("*"), 3500, 8000, None, 4096.0, 8192.0, None, 0.0, 2000.0, None, 15, 220),
+    ];
+
+    let mut table = tabled::Table::new(parse_resource_data(rrs, Filter::None));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert!(lines[1].contains("1000m") && lines[1].contains("3072Mi") && lines[1].contains("100"));
+    assert!(lines[2].contains("3500m") && lines[2].contains("1024Mi") && lines[2].contains("105"));
+    assert!(lines[3].contains("4500m (max 3500m)") && lines[3].contains("4096Mi (max 3072Mi)") && lines[3].contains("205 (max 105)"));
+}
+
+#[test]
+fn test_parse_resource_data_usage() {
+    let rrs = vec![
+        kubernetes::ResouceRequests::new(String::from("worker"), 1000, 4000, Some(2000), 1024.0, 4096.0, Some(2048.0), 0.0, 1000.0, Some(250.0), 10, 110),
+        kubernetes::ResouceRequests::new(String::from("worker2"), 1000, 4000, None, 1024.0, 4096.0, None, 0.0, 1000.0, None, 10, 110),
+    ];
+
+    let mut table = tabled::Table::new(parse_resource_data(rrs, Filter::None));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert!(lines[1].contains("2000m (50.00%)") && lines[1].contains("2048.00Mi (50.00%)") && lines[1].contains("250Mi (25.00%)"));
+    assert!(lines[1].contains("200.00%"));
+    assert_eq!(lines[2].matches("unknown").count(), 5);
+}
+
+#[test]
+fn test_parse_resource_data_efficiency() {
+    let rrs = vec![
+        kubernetes::ResouceRequests::new(String::from("*"), 10000, 28000, Some(1300), 3072.0, 16384.0, Some(2024.0), 0.0, 4000.0, None, 30, 440),
+        kubernetes::ResouceRequests::new(String::from("busy"), 1000, 4000, Some(900), 1024.0, 4096.0, Some(1024.0), 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("unknown"), 1000, 4000, None, 1024.0, 4096.0, None, 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("idle"), 8000, 16000, Some(300), 1024.0, 4096.0, Some(900.0), 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("empty"), 0, 4000, Some(100), 0.0, 4096.0, Some(100.0), 0.0, 1000.0, None, 0, 110),
+    ];
+
+    let mut table = tabled::Table::new(parse_resource_data(rrs, Filter::Efficiency));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let names: Vec<&str> = table.lines().skip(1).map(|l| l.split_whitespace().next().unwrap()).collect();
+
+    assert_eq!(names, vec!["idle", "busy", "unknown", "empty", "*"]);
+    assert!(table.lines().nth(1).unwrap().contains("3.75%"));
+    assert_eq!(format_efficiency(Some(100.0), 0.0), "-");
+}
+
+#[test]
+fn test_get_usage_stats() {
+    let samples: Vec<f32> = (1..=20).map(|n| n as f32 * 100.0).collect();
+
+    assert_eq!(get_usage_stats(&samples), Some([100.0, 1050.0, 1900.0, 2000.0]));
+    assert_eq!(get_usage_stats(&[300.0]), Some([300.0, 300.0, 300.0, 300.0]));
+    assert_eq!(get_usage_stats(&[]), None);
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("500ms"), Ok(std::time::Duration::from_millis(500)));
+    assert_eq!(parse_duration("10s"), Ok(std::time::Duration::from_secs(10)));
+    assert_eq!(parse_duration("2m"), Ok(std::time::Duration::from_secs(120)));
+    assert!(parse_duration("10").is_err());
+    assert!(parse_duration("s").is_err());
+}
+
+#[test]
+fn test_parse_rightsize_data() {
+    let containers = vec![
+        kubernetes::ContainerUsage {
+            namespace: String::from("default"),
+            workload: String::from("Deployment/web"),
+            container: String::from("web"),
+            replicas: 3,
+            resources: kubernetes::ContainerResources { cpu_requests: 1000, mem_requests: 1024.0, ..Default::default() },
+            cpu: vec![100, 200, 300],
+            mem: vec![200.0, 250.0, 300.0],
+            ..Default::default()
+        },
+        kubernetes::ContainerUsage {
+            namespace: String::from("default"),
+            workload: String::from("Deployment/idle"),
+            container: String::from("idle"),
+            replicas: 1,
+            ..Default::default()
+        },
+    ];
+
+    assert_eq!(get_recommendation(&containers[0], 20.0), Some((360, Some(360), 360.0, Some(360.0))));
+    assert_eq!(get_recommendation(&containers[1], 20.0), None);
+
+    let (rows, reclaimed) = parse_rightsize_data(&containers, 20.0, Filter::None);
+    assert_eq!(reclaimed, (1920, 1992.0));
+
+    let mut table = tabled::Table::new(rows);
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert!(lines[1].contains("1000m -> 360m") && lines[1].contains("1024Mi -> 360Mi") && lines[1].contains("1920m"));
+    assert!(lines[2].contains("unknown"));
+}
+
+#[test]
+fn test_get_recommendation_limits() {
+    let single = kubernetes::ContainerUsage { cpu: vec![300], mem: vec![300.0], ..Default::default() };
+    assert_eq!(get_recommendation(&single, 20.0), Some((360, None, 360.0, None)));
+
+    let peak = kubernetes::ContainerUsage { cpu_peak: Some(500), mem_peak: Some(1000.0), ..single };
+    assert_eq!(get_recommendation(&peak, 20.0), Some((360, Some(600), 360.0, Some(1200.0))));
+}
+
+#[test]
+fn test_parse_idle_data() {
+    let now = "2023-02-01T00:00:00Z".parse().unwrap();
+    let namespaces = vec![
+        kubernetes::NamespaceActivity { name: String::from("busy"), requests: (1000, 1024.0, 0.0, 2), cpu: vec![400, 600], ..Default::default() },
+        kubernetes::NamespaceActivity { name: String::from("quiet"), requests: (500, 512.0, 0.0, 1), cpu: vec![2, 5], ..Default::default() },
+        kubernetes::NamespaceActivity {
+            name: String::from("preview"),
+            requests: (2000, 4096.0, 0.0, 1),
+            last_activity: Some("2023-01-03T00:00:00Z".parse().unwrap()),
+            ..Default::default()
+        },
+        kubernetes::NamespaceActivity { name: String::from("empty"), cpu: vec![0], ..Default::default() },
+    ];
+
+    let (rows, reserved) = parse_idle_data(&namespaces, 10, 7, now);
+    assert_eq!(reserved, (2500, 4608.0));
+
+    let mut table = tabled::Table::new(rows);
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].contains("quiet") && lines[1].contains("cpu usage 5m below 10m"));
+    assert!(lines[2].contains("preview") && lines[2].contains("29d ago") && lines[2].contains("no new pods or restarts for 29d"));
+}
+
+#[test]
+fn test_parse_stuck_namespace_data() {
+    let pod = |namespace: &str, cpu_requests: u32, restarts: i32| kubernetes::StuckPod {
+        namespace: String::from(namespace),
+        name: String::from("pod"),
+        node: String::from("node"),
+        cpu_requests,
+        mem_requests: 256.0,
+        restarts,
+        reasons: vec![String::from("app CrashLoopBackOff")],
+    };
+    let pods = vec![pod("shop", 500, 3), pod("shop", 250, 1), pod("tools", 100, 0)];
+
+    let mut table = tabled::Table::new(parse_stuck_namespace_data(&pods));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<Vec<&str>> = table.lines().skip(1).map(|l| l.split_whitespace().collect()).collect();
+
+    assert_eq!(lines, vec![
+        vec!["shop", "2", "750m", "512Mi", "4"],
+        vec!["tools", "1", "100m", "256Mi", "0"],
+        vec!["*", "3", "850m", "768Mi", "4"],
+    ]);
+}
+
+#[test]
+fn test_parse_audit_data() {
+    let container = |workload: &str, missing: Vec<&'static str>| kubernetes::ContainerUsage {
+        namespace: String::from("shop"),
+        workload: String::from(workload),
+        container: String::from("app"),
+        replicas: 2,
+        missing,
+        cpu: vec![100, 300],
+        mem: vec![128.0, 256.0],
+        ..Default::default()
+    };
+    let containers = vec![
+        container("Deployment/api", vec!["cpu limits"]),
+        container("Deployment/web", vec!["mem requests", "mem limits"]),
+        container("Deployment/db", vec![]),
+        kubernetes::ContainerUsage { init: true, ..container("Deployment/worker", vec!["cpu requests"]) },
+    ];
+
+    let (rows, workloads) = parse_audit_data(&containers, false);
+    assert_eq!((rows.len(), workloads), (3, 3));
+
+    let (rows, workloads) = parse_audit_data(&containers, true);
+    assert_eq!((rows.len(), workloads), (2, 2));
+
+    let mut table = tabled::Table::new(rows);
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+
+    assert!(table.contains("shop/Deployment/web") && table.contains("mem requests") && table.contains("200m") && table.contains("192Mi"));
+    assert!(!table.contains("mem limits"));
+    assert!(table.contains("app (init)"));
+}
+
+#[test]
+fn test_parse_oom_risk_data() {
+    let container = |pod: &str, limit: f32, usage: f32| kubernetes::ContainerMemory {
+        namespace: String::from("default"),
+        pod: String::from(pod),
+        container: String::from("app"),
+        node: String::from("worker"),
+        limit,
+        usage,
+    };
+    let containers = vec![container("fine", 1024.0, 512.0), container("close", 512.0, 470.0), container("closer", 256.0, 250.0)];
+
+    let mut table = tabled::Table::new(parse_oom_risk_data(&containers, 90.0));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let pods: Vec<&str> = table.lines().skip(1).map(|l| l.split_whitespace().nth(1).unwrap()).collect();
+
+    assert_eq!(pods, vec!["closer", "close"]);
+    assert!(table.contains("250.00Mi (97.66%)"));
+
+    let rrs = vec![
+        kubernetes::ResouceRequests::new(String::from("worker"), 1000, 4000, Some(2000), 2048.0, 4096.0, Some(3800.0), 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("worker2"), 1000, 4000, Some(2000), 2048.0, 4096.0, Some(1024.0), 0.0, 1000.0, None, 10, 110),
+        kubernetes::ResouceRequests::new(String::from("*"), 2000, 8000, Some(4000), 4096.0, 8192.0, Some(4824.0), 0.0, 2000.0, None, 20, 220),
+    ];
+
+    let mut table = tabled::Table::new(parse_node_memory_data(&rrs, 90.0));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains("worker") && lines[1].contains("2048Mi (50.00%)") && lines[1].contains("3800.00Mi (92.77%)"));
+}
+
+#[test]
+fn test_parse_node_pressure_data() {
+    let event = |pod: &str, container: Option<&str>, reason: &str| kubernetes::PressureEvent {
+        namespace: String::from("default"),
+        pod: String::from(pod),
+        container: container.map(String::from),
+        reason: String::from(reason),
+        time: None,
+        message: String::from("The node was low on resource: memory."),
+    };
+    let nodes = vec![kubernetes::NodePressure {
+        name: String::from("worker"),
+        conditions: vec![(String::from("MemoryPressure"), String::from("True"))],
+        mem_allocatable: 4096.0,
+        mem_requests: 2048.0,
+        mem_usage: Some(3900.0),
+        events: vec![event("cache", Some("redis"), "OOMKilled"), event("batch", None, "Evicted"), event("web", None, "Evicted")],
+    }];
+
+    let mut table = tabled::Table::new(parse_node_pressure_data(&nodes));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let cells: Vec<&str> = table.lines().nth(1).unwrap().split_whitespace().collect();
+
+    assert_eq!(cells, vec!["worker", "True", "Unknown", "Unknown", "2048Mi", "(50.00%)", "3900.00Mi", "(95.21%)", "1", "2"]);
+
+    let mut table = tabled::Table::new(parse_pressure_event_data(&nodes));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+
+    assert_eq!(table.lines().count(), 4);
+    assert!(table.contains("default/cache") && table.contains("redis") && table.contains("unknown"));
+}
+
+#[test]
+fn test_parse_volume_data() {
+    let volumes = vec![
+        (String::from("default/web"), String::from("cache"), 100.0, None),
+        (String::from("default/web"), String::from("data"), 500.0, Some(1024.0)),
+    ];
+
+    let mut table = tabled::Table::new(parse_volume_data(&volumes));
+    table.with(tabled::Style::blank());
+    let table = table.to_string();
+    let lines: Vec<&str> = table.lines().collect();
+
+    assert!(lines[1].contains("data") && lines[1].contains("500Mi") && lines[1].contains("1024Mi (48.83%)"));
+    assert!(lines[2].contains("cache") && lines[2].contains("unknown"));
+}